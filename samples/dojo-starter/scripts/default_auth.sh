@@ -1,18 +1,11 @@
 #!/bin/bash
 set -euo pipefail
-pushd $(dirname "$0")/..
 
-export WORLD_ADDRESS="0x7d1f066a910bd86f532fa9ca66766722c20d47462fb99fb2fb0e1030262f9c5";
+# Grants the writers the manifest asks for, Position and Moves to the actions, through Keiko.
+# Keiko started with --auto-auth does this after every migration on its own.
+KEIKO_URL=${KEIKO_URL:-"http://localhost:3000"}
 
-# enable system -> component authorizations
-COMPONENTS=("Position" "Moves" )
+curl --fail-with-body -s -X POST "$KEIKO_URL/api/auth/apply"
 
-for component in ${COMPONENTS[@]}; do
-    sozo auth writer $component spawn --world $WORLD_ADDRESS
-done
-
-for component in ${COMPONENTS[@]}; do
-    sozo auth writer $component move --world $WORLD_ADDRESS
-done
-
-echo "Default authorizations have been successfully set."
\ No newline at end of file
+echo
+echo "Default authorizations have been successfully set."
@@ -0,0 +1,679 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context};
+use num_bigint::{BigInt, BigUint, Sign};
+use serde_json::{Map, Value};
+use starknet::core::types::contract::{AbiEntry, AbiEnum, AbiFunction, AbiStruct};
+use starknet::core::types::FieldElement;
+use starknet::core::utils::cairo_short_string_to_felt;
+
+/// A Cairo 1 contract ABI, indexed to encode human-readable arguments and decode results.
+///
+/// Felts, addresses and class hashes are hex strings (or short strings, when they aren't a number),
+/// integers up to 64 bits are numbers, wider ones decimal strings. Structs are objects, enums
+/// are the variant name or `{ "Variant": value }`, `Option`s are null or their value.
+#[derive(Debug, Clone, Default)]
+pub struct Abi {
+    functions: Vec<AbiFunction>,
+    structs: HashMap<String, AbiStruct>,
+    enums: HashMap<String, AbiEnum>,
+}
+
+enum Ty<'a> {
+    Unit,
+    Felt,
+    Bool,
+    Unsigned(u32),
+    Signed(u32),
+    U256,
+    ByteArray,
+    Array(&'a str),
+    Tuple(Vec<&'a str>),
+    Named(&'a str),
+}
+
+const FELT_TYPES: [&str; 6] = [
+    "core::felt252",
+    "core::starknet::contract_address::ContractAddress",
+    "core::starknet::class_hash::ClassHash",
+    "core::starknet::eth_address::EthAddress",
+    "core::starknet::storage_access::StorageAddress",
+    "core::bytes_31::bytes31",
+];
+
+/// How many bytes of a ByteArray fit in one felt.
+const BYTES_PER_WORD: usize = 31;
+
+impl Abi {
+    pub fn parse(abi: Value) -> anyhow::Result<Self> {
+        let entries: Vec<AbiEntry> = serde_json::from_value(abi).context("not a Cairo 1 ABI")?;
+
+        let mut abi = Self::default();
+        abi.add(entries);
+        Ok(abi)
+    }
+
+    fn add(&mut self, entries: Vec<AbiEntry>) {
+        for entry in entries {
+            match entry {
+                AbiEntry::Function(function) => self.functions.push(function),
+                AbiEntry::Interface(interface) => self.add(interface.items),
+                AbiEntry::Struct(abi_struct) => {
+                    self.structs.insert(abi_struct.name.clone(), abi_struct);
+                }
+                AbiEntry::Enum(abi_enum) => {
+                    self.enums.insert(abi_enum.name.clone(), abi_enum);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn functions(&self) -> &[AbiFunction] {
+        &self.functions
+    }
+
+    pub fn function(&self, name: &str) -> Option<&AbiFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn structs(&self) -> impl Iterator<Item = &AbiStruct> {
+        self.structs.values()
+    }
+
+    pub fn find_struct(&self, name: &str) -> Option<&AbiStruct> {
+        self.structs.get(name)
+    }
+
+    /**
+     * a value in the shape `decode` gives, whichever way it was written: `"0x2a"`, `42` and `"42"` are one u32
+     */
+    pub fn normalize(&self, ty: &str, value: &Value) -> anyhow::Result<Value> {
+        let mut felts = vec![];
+        self.encode(ty, value, &mut felts)?;
+        self.decode(ty, &mut felts.into_iter())
+    }
+
+    /**
+     * a scalar the way Torii stores it: felts, u64 and wider integers as 0x prefixed 64 digit hex,
+     * the others as `decode` gives them. Structs, arrays and enums with data aren't scalars
+     */
+    pub fn torii_scalar(&self, ty: &str, value: &Value) -> anyhow::Result<Value> {
+        let mut felts = vec![];
+        self.encode(ty, value, &mut felts)?;
+
+        Ok(match classify(ty) {
+            Ty::Felt => Value::String(format!("{:#066x}", felts[0])),
+            Ty::Unsigned(bits) if bits > 32 => Value::String(format!("{:#066x}", felts[0])),
+            Ty::U256 => {
+                let value = (to_biguint(felts[1]) << 128u32) + to_biguint(felts[0]);
+                Value::String(format!("0x{:0>64}", value.to_str_radix(16)))
+            }
+            Ty::Array(_) | Ty::Tuple(_) => bail!("{} is not a scalar", ty),
+            _ => match self.decode(ty, &mut felts.into_iter())? {
+                Value::Object(_) | Value::Array(_) => bail!("{} is not a scalar", ty),
+                scalar => scalar,
+            }
+        })
+    }
+
+    /**
+     * the bits Dojo packs each felt of a serialized value into, enums only without data
+     */
+    pub fn packed_layout(&self, ty: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(match classify(ty) {
+            Ty::Unit => vec![],
+            Ty::Felt | Ty::Signed(_) => vec![251],
+            Ty::Bool => vec![1],
+            Ty::Unsigned(bits) => vec![bits as u8],
+            Ty::U256 => vec![128, 128],
+            Ty::Tuple(items) => items.iter()
+                .map(|item| self.packed_layout(item))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .concat(),
+            Ty::Named(name) => {
+                if let Some(abi_struct) = self.structs.get(name) {
+                    abi_struct.members.iter()
+                        .map(|member| self.packed_layout(&member.r#type))
+                        .collect::<anyhow::Result<Vec<_>>>()?
+                        .concat()
+                } else if let Some(abi_enum) = self.enums.get(name) {
+                    if abi_enum.variants.iter().any(|variant| variant.r#type != "()") {
+                        bail!("{} has variants with data, their layout can't be packed", name);
+                    }
+                    vec![8]
+                } else {
+                    bail!("unknown type {}", name);
+                }
+            }
+            Ty::ByteArray | Ty::Array(_) => bail!("{} has no fixed size, it can't be packed", ty)
+        })
+    }
+
+    /**
+     * encodes the arguments of a function, given as an array, an object by name, or a single value
+     */
+    pub fn encode_inputs(&self, function: &AbiFunction, args: &Value) -> anyhow::Result<Vec<FieldElement>> {
+        let args: Vec<&Value> = match args {
+            Value::Null => vec![],
+            Value::Array(args) => args.iter().collect(),
+            Value::Object(args) => function.inputs.iter()
+                .map(|input| args.get(&input.name).ok_or_else(|| anyhow!("missing argument `{}`", input.name)))
+                .collect::<anyhow::Result<_>>()?,
+            arg => vec![arg]
+        };
+
+        if args.len() != function.inputs.len() {
+            bail!("{} takes {} arguments, got {}", function.name, function.inputs.len(), args.len());
+        }
+
+        let mut calldata = vec![];
+        for (input, arg) in function.inputs.iter().zip(args) {
+            self.encode(&input.r#type, arg, &mut calldata)
+                .with_context(|| format!("invalid argument `{}`", input.name))?;
+        }
+
+        Ok(calldata)
+    }
+
+    /**
+     * decodes what a function returned, a single output is returned as is, several as an array
+     */
+    pub fn decode_outputs(&self, function: &AbiFunction, result: &[FieldElement]) -> anyhow::Result<Value> {
+        let mut felts = result.iter().copied();
+
+        let mut outputs = function.outputs.iter()
+            .map(|output| self.decode(&output.r#type, &mut felts))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(match outputs.len() {
+            0 => Value::Null,
+            1 => outputs.remove(0),
+            _ => Value::Array(outputs)
+        })
+    }
+
+    pub fn encode(&self, ty: &str, value: &Value, out: &mut Vec<FieldElement>) -> anyhow::Result<()> {
+        match classify(ty) {
+            Ty::Unit => match value {
+                Value::Null => {}
+                _ => bail!("expected null for {}", ty)
+            },
+            Ty::Felt => out.push(match value {
+                Value::String(text) if !is_number(text) => cairo_short_string_to_felt(text)
+                    .map_err(|_| anyhow!("`{}` is neither a number nor a short string", text))?,
+                _ => {
+                    let number = to_bigint(value)?;
+                    let felt = to_felt(number.magnitude())?;
+                    if number.sign() == Sign::Minus { -felt } else { felt }
+                }
+            }),
+            Ty::Bool => match value {
+                Value::Bool(value) => out.push(if *value { FieldElement::ONE } else { FieldElement::ZERO }),
+                _ => bail!("expected true or false")
+            },
+            Ty::Unsigned(bits) => {
+                let number = to_bigint(value)?;
+                if number.sign() == Sign::Minus || number.bits() > bits as u64 {
+                    bail!("{} doesn't fit in a u{}", number, bits);
+                }
+                out.push(to_felt(number.magnitude())?);
+            }
+            Ty::Signed(bits) => {
+                let number = to_bigint(value)?;
+                let limit = BigInt::from(1) << (bits - 1);
+                if number >= limit || number < -limit {
+                    bail!("{} doesn't fit in an i{}", number, bits);
+                }
+                let felt = to_felt(number.magnitude())?;
+                out.push(if number.sign() == Sign::Minus { -felt } else { felt });
+            }
+            Ty::U256 => {
+                let number = to_bigint(value)?;
+                if number.sign() == Sign::Minus || number.bits() > 256 {
+                    bail!("{} doesn't fit in a u256", number);
+                }
+                let number = number.magnitude();
+                let mask = (BigUint::from(1u8) << 128) - 1u8;
+                out.push(to_felt(&(number & &mask))?);
+                out.push(to_felt(&(number >> 128))?);
+            }
+            Ty::ByteArray => {
+                let Value::String(text) = value else {
+                    bail!("expected a string");
+                };
+                let chunks: Vec<&[u8]> = text.as_bytes().chunks(BYTES_PER_WORD).collect();
+                let (words, pending) = match chunks.split_last() {
+                    Some((last, words)) if last.len() < BYTES_PER_WORD => (words, *last),
+                    _ => (chunks.as_slice(), &[][..]),
+                };
+
+                out.push(words.len().into());
+                for word in words {
+                    out.push(FieldElement::from_byte_slice_be(word)?);
+                }
+                out.push(FieldElement::from_byte_slice_be(pending)?);
+                out.push(pending.len().into());
+            }
+            Ty::Array(item) => {
+                let Value::Array(items) = value else {
+                    bail!("expected an array");
+                };
+                out.push(items.len().into());
+                for item_value in items {
+                    self.encode(item, item_value, out)?;
+                }
+            }
+            Ty::Tuple(items) => match value {
+                Value::Array(values) if values.len() == items.len() => {
+                    for (item, item_value) in items.iter().zip(values) {
+                        self.encode(item, item_value, out)?;
+                    }
+                }
+                _ => bail!("expected an array of {} values", items.len())
+            },
+            Ty::Named(name) => {
+                if let Some(abi_struct) = self.structs.get(name) {
+                    return self.encode_struct(abi_struct, value, out);
+                }
+                if let Some(abi_enum) = self.enums.get(name) {
+                    return self.encode_enum(abi_enum, value, out);
+                }
+                bail!("unknown type {}", name);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn encode_struct(&self, abi_struct: &AbiStruct, value: &Value, out: &mut Vec<FieldElement>) -> anyhow::Result<()> {
+        let values: Vec<&Value> = match value {
+            Value::Object(fields) => abi_struct.members.iter()
+                .map(|member| fields.get(&member.name).ok_or_else(|| anyhow!("missing field `{}`", member.name)))
+                .collect::<anyhow::Result<_>>()?,
+            Value::Array(values) if values.len() == abi_struct.members.len() => values.iter().collect(),
+            _ => bail!("expected an object with {}", abi_struct.members.iter().map(|member| member.name.as_str()).collect::<Vec<_>>().join(", "))
+        };
+
+        for (member, member_value) in abi_struct.members.iter().zip(values) {
+            self.encode(&member.r#type, member_value, out)
+                .with_context(|| format!("invalid field `{}`", member.name))?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_enum(&self, abi_enum: &AbiEnum, value: &Value, out: &mut Vec<FieldElement>) -> anyhow::Result<()> {
+        let is_option = abi_enum.name.starts_with("core::option::Option::<");
+
+        let (variant, variant_value) = match value {
+            Value::Null if is_option => ("None", &Value::Null),
+            Value::String(variant) if abi_enum.variants.iter().any(|candidate| &candidate.name == variant) => {
+                (variant.as_str(), &Value::Null)
+            }
+            Value::Object(variants) if variants.len() == 1 => {
+                let (variant, variant_value) = variants.iter().next().expect("one variant");
+                (variant.as_str(), variant_value)
+            }
+            _ if is_option => ("Some", value),
+            _ => bail!("expected one of {}", abi_enum.variants.iter().map(|variant| variant.name.as_str()).collect::<Vec<_>>().join(", "))
+        };
+
+        let (index, member) = abi_enum.variants.iter().enumerate()
+            .find(|(_, candidate)| candidate.name == variant)
+            .ok_or_else(|| anyhow!("{} has no variant {}", abi_enum.name, variant))?;
+
+        out.push(index.into());
+        self.encode(&member.r#type, variant_value, out)
+            .with_context(|| format!("invalid variant `{}`", variant))
+    }
+
+    pub fn decode(&self, ty: &str, felts: &mut impl Iterator<Item = FieldElement>) -> anyhow::Result<Value> {
+        Ok(match classify(ty) {
+            Ty::Unit => Value::Null,
+            Ty::Felt => Value::String(format!("{:#x}", next(felts, ty)?)),
+            Ty::Bool => Value::Bool(next(felts, ty)? != FieldElement::ZERO),
+            Ty::Unsigned(bits) => number_value(BigInt::from(to_biguint(next(felts, ty)?)), bits),
+            Ty::Signed(bits) => {
+                let felt = next(felts, ty)?;
+                let number = if to_biguint(felt) > to_biguint(FieldElement::MAX) / 2u8 {
+                    -BigInt::from(to_biguint(-felt))
+                } else {
+                    BigInt::from(to_biguint(felt))
+                };
+                number_value(number, bits)
+            }
+            Ty::U256 => {
+                let low = to_biguint(next(felts, ty)?);
+                let high = to_biguint(next(felts, ty)?);
+                Value::String(((high << 128u32) + low).to_string())
+            }
+            Ty::ByteArray => {
+                let words = felt_to_usize(next(felts, ty)?)?;
+                let mut bytes = vec![];
+                for _ in 0..words {
+                    bytes.extend_from_slice(&next(felts, ty)?.to_bytes_be()[32 - BYTES_PER_WORD..]);
+                }
+                let pending = next(felts, ty)?.to_bytes_be();
+                let pending_len = felt_to_usize(next(felts, ty)?)?.min(BYTES_PER_WORD);
+                bytes.extend_from_slice(&pending[32 - pending_len..]);
+
+                Value::String(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Ty::Array(item) => {
+                let len = felt_to_usize(next(felts, ty)?)?;
+                let items = (0..len)
+                    .map(|_| self.decode(item, felts))
+                    .collect::<anyhow::Result<_>>()?;
+                Value::Array(items)
+            }
+            Ty::Tuple(items) => Value::Array(
+                items.iter().map(|item| self.decode(item, felts)).collect::<anyhow::Result<_>>()?
+            ),
+            Ty::Named(name) => {
+                if let Some(abi_struct) = self.structs.get(name) {
+                    let mut fields = Map::new();
+                    for member in &abi_struct.members {
+                        fields.insert(member.name.clone(), self.decode(&member.r#type, felts)?);
+                    }
+                    return Ok(Value::Object(fields));
+                }
+
+                let abi_enum = self.enums.get(name).ok_or_else(|| anyhow!("unknown type {}", name))?;
+                let index = felt_to_usize(next(felts, ty)?)?;
+                let variant = abi_enum.variants.get(index)
+                    .ok_or_else(|| anyhow!("{} has no variant {}", name, index))?;
+                let value = self.decode(&variant.r#type, felts)?;
+
+                if name.starts_with("core::option::Option::<") {
+                    value
+                } else if variant.r#type == "()" {
+                    Value::String(variant.name.clone())
+                } else {
+                    Value::Object(Map::from_iter([(variant.name.clone(), value)]))
+                }
+            }
+        })
+    }
+}
+
+fn classify(ty: &str) -> Ty<'_> {
+    let ty = ty.trim();
+
+    if ty == "()" {
+        return Ty::Unit;
+    }
+    if let Some(items) = ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+        return Ty::Tuple(split_top_level(items));
+    }
+    if let Some(item) = ty.strip_prefix("core::array::Array::<").or_else(|| ty.strip_prefix("core::array::Span::<")) {
+        return Ty::Array(item.strip_suffix('>').unwrap_or(item));
+    }
+    if FELT_TYPES.contains(&ty) {
+        return Ty::Felt;
+    }
+
+    match ty {
+        "core::bool" => Ty::Bool,
+        "core::integer::u256" => Ty::U256,
+        "core::byte_array::ByteArray" => Ty::ByteArray,
+        _ => {
+            let integer = ty.strip_prefix("core::integer::")
+                .and_then(|integer| integer.split_at_checked(1))
+                .and_then(|(sign, bits)| Some((sign, bits.parse::<u32>().ok()?)));
+
+            match integer {
+                Some(("u", bits)) if bits <= 128 => Ty::Unsigned(bits),
+                Some(("i", bits)) if bits <= 128 => Ty::Signed(bits),
+                _ => Ty::Named(ty)
+            }
+        }
+    }
+}
+
+/// Splits `A, B::<C, D>, (E, F)` on the commas that aren't nested.
+fn split_top_level(types: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, c) in types.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(types[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let last = types[start..].trim();
+    if !last.is_empty() {
+        items.push(last);
+    }
+    items
+}
+
+fn is_number(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+
+    match digits.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    }
+}
+
+fn next(felts: &mut impl Iterator<Item = FieldElement>, ty: &str) -> anyhow::Result<FieldElement> {
+    felts.next().ok_or_else(|| anyhow!("ran out of values decoding {}", ty))
+}
+
+fn to_bigint(value: &Value) -> anyhow::Result<BigInt> {
+    match value {
+        Value::Number(number) => number.as_i64().map(BigInt::from)
+            .or_else(|| number.as_u64().map(BigInt::from))
+            .ok_or_else(|| anyhow!("{} is not an integer", number)),
+        Value::String(text) if is_number(text) => {
+            let (negative, digits) = match text.strip_prefix('-') {
+                Some(digits) => (true, digits),
+                None => (false, text.as_str())
+            };
+            let magnitude = match digits.strip_prefix("0x") {
+                Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+                None => BigInt::parse_bytes(digits.as_bytes(), 10)
+            }.ok_or_else(|| anyhow!("`{}` is not a number", text))?;
+
+            Ok(if negative { -magnitude } else { magnitude })
+        }
+        _ => Err(anyhow!("expected a number, or a decimal or hex string"))
+    }
+}
+
+fn to_felt(number: &BigUint) -> anyhow::Result<FieldElement> {
+    FieldElement::from_byte_slice_be(&number.to_bytes_be())
+        .map_err(|_| anyhow!("{} doesn't fit in a felt", number))
+}
+
+fn to_biguint(felt: FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&felt.to_bytes_be())
+}
+
+fn felt_to_usize(felt: FieldElement) -> anyhow::Result<usize> {
+    usize::try_from(to_biguint(felt)).map_err(|_| anyhow!("{:#x} is not a length", felt))
+}
+
+/// Integers wider than 64 bits are strings, JSON parsers would round them.
+fn number_value(number: BigInt, bits: u32) -> Value {
+    match i64::try_from(&number) {
+        Ok(number) if bits <= 64 => Value::from(number),
+        _ => match u64::try_from(&number) {
+            Ok(number) if bits <= 64 => Value::from(number),
+            _ => Value::String(number.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    fn abi() -> Abi {
+        Abi::parse(json!([
+            {
+                "type": "struct",
+                "name": "dojo_examples::models::Vec2",
+                "members": [
+                    { "name": "x", "type": "core::integer::u32" },
+                    { "name": "y", "type": "core::integer::u32" }
+                ]
+            },
+            {
+                "type": "enum",
+                "name": "dojo_examples::models::Direction",
+                "variants": [
+                    { "name": "None", "type": "()" },
+                    { "name": "Left", "type": "()" },
+                    { "name": "Right", "type": "()" }
+                ]
+            },
+            {
+                "type": "enum",
+                "name": "core::option::Option::<core::integer::u8>",
+                "variants": [
+                    { "name": "Some", "type": "core::integer::u8" },
+                    { "name": "None", "type": "()" }
+                ]
+            },
+            {
+                "type": "function",
+                "name": "teleport",
+                "inputs": [
+                    { "name": "player", "type": "core::starknet::contract_address::ContractAddress" },
+                    { "name": "to", "type": "dojo_examples::models::Vec2" },
+                    { "name": "direction", "type": "dojo_examples::models::Direction" },
+                    { "name": "delta", "type": "core::integer::i8" },
+                    { "name": "amount", "type": "core::integer::u256" },
+                    { "name": "name", "type": "core::byte_array::ByteArray" },
+                    { "name": "items", "type": "core::array::Array::<core::integer::u8>" },
+                    { "name": "limit", "type": "core::option::Option::<core::integer::u8>" }
+                ],
+                "outputs": [],
+                "state_mutability": "external"
+            },
+            {
+                "type": "function",
+                "name": "position",
+                "inputs": [],
+                "outputs": [{ "type": "dojo_examples::models::Vec2" }, { "type": "core::bool" }],
+                "state_mutability": "view"
+            }
+        ])).unwrap()
+    }
+
+    fn felts(values: &[u64]) -> Vec<FieldElement> {
+        values.iter().map(|value| FieldElement::from(*value)).collect()
+    }
+
+    #[test]
+    fn encodes_inputs_by_name_and_by_position() {
+        let abi = abi();
+        let teleport = abi.function("teleport").unwrap();
+
+        let by_name = abi.encode_inputs(teleport, &json!({
+            "player": "0x1",
+            "to": { "x": 10, "y": "0x14" },
+            "direction": "Right",
+            "delta": -1,
+            "amount": "340282366920938463463374607431768211457",
+            "name": "hello",
+            "items": [1, 2],
+            "limit": null
+        })).unwrap();
+        let by_position = abi.encode_inputs(teleport, &json!([
+            "0x1", [10, 20], { "Right": null }, "-1", "340282366920938463463374607431768211457", "hello", ["1", "0x2"], null
+        ])).unwrap();
+
+        let mut expected = felts(&[1, 10, 20, 2]);
+        expected.push(-FieldElement::ONE);
+        // u256 is low then high: 2**128 + 1
+        expected.extend(felts(&[1, 1]));
+        // no full word, the pending word and its length
+        expected.extend([FieldElement::ZERO, cairo_short_string_to_felt("hello").unwrap(), FieldElement::from(5u8)]);
+        expected.extend(felts(&[2, 1, 2]));
+        expected.extend(felts(&[1]));
+
+        assert_eq!(by_name, expected);
+        assert_eq!(by_position, expected);
+    }
+
+    #[test]
+    fn rejects_invalid_inputs() {
+        let abi = abi();
+        let teleport = abi.function("teleport").unwrap();
+        let valid = json!({
+            "player": "0x1", "to": { "x": 1, "y": 2 }, "direction": "Left", "delta": 0,
+            "amount": 0, "name": "", "items": [], "limit": 3
+        });
+        assert!(abi.encode_inputs(teleport, &valid).is_ok());
+
+        let invalid = |field: &str, value: Value| {
+            let mut args = valid.clone();
+            args[field] = value;
+            abi.encode_inputs(teleport, &args).unwrap_err()
+        };
+        assert!(format!("{:#}", invalid("items", json!([256]))).contains("doesn't fit in a u8"));
+        assert!(format!("{:#}", invalid("delta", json!(128))).contains("doesn't fit in an i8"));
+        assert!(format!("{:#}", invalid("direction", json!("Up"))).contains("expected one of None, Left, Right"));
+        assert!(format!("{:#}", invalid("to", json!({ "x": 1 }))).contains("missing field `y`"));
+
+        assert!(abi.encode_inputs(teleport, &json!(["0x1"])).is_err());
+        assert!(abi.encode_inputs(teleport, &json!({ "player": "0x1" })).is_err());
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let abi = abi();
+
+        let values = [
+            ("core::integer::u32", json!(42)),
+            ("core::integer::i8", json!(-5)),
+            ("core::integer::u128", json!("340282366920938463463374607431768211455")),
+            ("core::integer::u256", json!("115792089237316195423570985008687907853269984665640564039457584007913129639935")),
+            ("core::felt252", json!("0x2a")),
+            ("core::bool", json!(true)),
+            ("core::byte_array::ByteArray", json!("a string longer than the 31 bytes of a word")),
+            ("dojo_examples::models::Vec2", json!({ "x": 1, "y": 2 })),
+            ("dojo_examples::models::Direction", json!("Left")),
+            ("core::option::Option::<core::integer::u8>", json!(7)),
+            ("core::option::Option::<core::integer::u8>", Value::Null),
+            ("core::array::Span::<core::integer::u8>", json!([1, 2, 3])),
+            ("(core::integer::u8, core::bool)", json!([1, false])),
+        ];
+
+        for (ty, value) in values {
+            let mut felts = vec![];
+            abi.encode(ty, &value, &mut felts).unwrap();
+            assert_eq!(abi.decode(ty, &mut felts.into_iter()).unwrap(), value, "{}", ty);
+        }
+    }
+
+    #[test]
+    fn decodes_outputs() {
+        let abi = abi();
+        let position = abi.function("position").unwrap();
+
+        assert_eq!(abi.decode_outputs(position, &felts(&[3, 4, 1])).unwrap(), json!([{ "x": 3, "y": 4 }, true]));
+        assert!(abi.decode_outputs(position, &felts(&[3])).is_err());
+    }
+
+    #[test]
+    fn normalizes_the_ways_a_value_is_written() {
+        let abi = abi();
+
+        for written in [json!(42), json!("42"), json!("0x2a")] {
+            assert_eq!(abi.normalize("core::integer::u32", &written).unwrap(), json!(42));
+        }
+        assert_eq!(abi.normalize("core::felt252", &json!(42)).unwrap(), json!("0x2a"));
+    }
+}
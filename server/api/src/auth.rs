@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, SystemTime};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::accounts::{Account, Call, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
+use starknet::providers::Provider;
+use crate::chain::{self, StarknetProvider};
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::Genesis;
+use crate::manifest::{self, Manifest};
+use crate::server_state::ServerState;
+
+/// How often the migration hook looks for a new deployment manifest.
+const MIGRATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The `[auth]` section of keiko.toml, grants on top of the ones the manifests ask for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// model → the contracts, by name or address, that may write it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub writers: BTreeMap<String, Vec<String>>,
+    /// resource (a model, a contract or `world`) → the addresses that own it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub owners: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Writer,
+    Owner,
+}
+
+/// A role on a resource of the world: writing a model, or owning a model, a contract or the world.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Grant {
+    pub role: Role,
+    pub resource: String,
+    #[serde_as(as = "UfeHex")]
+    pub resource_id: FieldElement,
+    #[serde(flatten)]
+    pub grantee: Grantee,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Grantee {
+    /// the contract's name, when it's in the manifest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct AuthReport {
+    #[serde_as(as = "UfeHex")]
+    pub world_address: FieldElement,
+    pub world_owners: Vec<Grantee>,
+    pub namespaces: Vec<NamespacePermissions>,
+    pub drift: Drift,
+}
+
+#[derive(Serialize)]
+pub struct NamespacePermissions {
+    pub namespace: String,
+    pub models: Vec<ModelPermissions>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct ModelPermissions {
+    pub model: String,
+    #[serde_as(as = "UfeHex")]
+    pub resource_id: FieldElement,
+    pub writers: Vec<Grantee>,
+    pub owners: Vec<Grantee>,
+}
+
+/// The difference between the grants asked for and the ones in the world.
+#[derive(Serialize)]
+pub struct Drift {
+    /// asked for, but not granted
+    pub missing: Vec<Grant>,
+    /// granted, but not asked for. Owners only count for resources `[auth]` names owners of.
+    pub unexpected: Vec<Grant>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Applied {
+    #[serde_as(as = "Option<UfeHex>")]
+    pub transaction_hash: Option<FieldElement>,
+    pub granted: Vec<Grant>,
+    pub revoked: Vec<Grant>,
+    pub report: AuthReport,
+}
+
+/// Everything the world is asked about, resolved against the manifest.
+struct Resolved {
+    world_address: FieldElement,
+    /// (full name, namespace, resource id)
+    models: Vec<(String, String, FieldElement)>,
+    /// who may write or own something, the manifest's contracts, the dev accounts and whoever the config names
+    writer_candidates: BTreeSet<Grantee>,
+    owner_candidates: BTreeSet<Grantee>,
+    desired: BTreeSet<Grant>,
+    /// the resources the config names owners for
+    owned_resources: BTreeSet<FieldElement>,
+}
+
+impl Resolved {
+    fn new(state: &ServerState, config: &AuthConfig) -> anyhow::Result<Self> {
+        let manifest = Manifest::load(&state.manifest_base_dir)?;
+        let world_address = manifest.world.address
+            .map_or_else(|| FieldElement::from_hex_be(&state.world_address), Ok)?;
+
+        let mut models = vec![];
+        for model in &manifest.models {
+            models.push((
+                model.name.clone(),
+                manifest::namespace(&model.name).to_string(),
+                get_selector_from_name(&manifest.struct_name(model)?)?,
+            ));
+        }
+
+        let model_id = |name: &str| -> anyhow::Result<(String, FieldElement)> {
+            let model = manifest.find_model(name)
+                .ok_or_else(|| anyhow!("no model `{}` in the manifest", name))?;
+            Ok((model.name.clone(), get_selector_from_name(&manifest.struct_name(model)?)?))
+        };
+
+        let contract = |contract: &str| -> anyhow::Result<Grantee> {
+            if let Some(found) = manifest.find_contract(contract)? {
+                let address = found.address.ok_or_else(|| anyhow!("{} isn't deployed", found.name))?;
+                return Ok(Grantee { name: Some(found.name.clone()), address });
+            }
+
+            let address = manifest::literal_address(contract)
+                .ok_or_else(|| anyhow!("no contract `{}` in the manifest", contract))?;
+            Ok(Grantee { name: None, address })
+        };
+
+        let mut writer_candidates: BTreeSet<Grantee> = manifest.contracts.iter()
+            .filter_map(|found| found.address.map(|address| Grantee { name: Some(found.name.clone()), address }))
+            .collect();
+
+        let mut owner_candidates: BTreeSet<Grantee> = Genesis::read(&state.genesis_path())
+            .map(|genesis| genesis.dev_accounts())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|account| Grantee { name: None, address: account.address })
+            .collect();
+
+        let mut desired = BTreeSet::new();
+
+        // the manifests list the models a contract writes by struct name
+        let manifest_writers = manifest.contracts.iter()
+            .flat_map(|found| found.writes.iter().map(move |model| (model.clone(), found.name.clone())));
+        let config_writers = config.writers.iter()
+            .flat_map(|(model, contracts)| contracts.iter().map(move |found| (model.clone(), found.clone())));
+
+        for (model, found) in manifest_writers.chain(config_writers) {
+            let (resource, resource_id) = model_id(&model)?;
+            let grantee = contract(&found)?;
+            writer_candidates.insert(grantee.clone());
+            desired.insert(Grant { role: Role::Writer, resource, resource_id, grantee });
+        }
+
+        let mut owned_resources = BTreeSet::new();
+        for (resource, owners) in &config.owners {
+            let (resource, resource_id) = if resource == "world" {
+                (resource.clone(), FieldElement::ZERO)
+            } else if let Ok(model) = model_id(resource) {
+                model
+            } else {
+                let found = contract(resource)?;
+                (found.name.unwrap_or_else(|| resource.clone()), found.address)
+            };
+            owned_resources.insert(resource_id);
+
+            for owner in owners {
+                let address = FieldElement::from_hex_be(owner)
+                    .map_err(|_| anyhow!("owner `{}` of {} is not an address", owner, resource))?;
+                let grantee = Grantee { name: None, address };
+                owner_candidates.insert(grantee.clone());
+                desired.insert(Grant { role: Role::Owner, resource: resource.clone(), resource_id, grantee });
+            }
+        }
+
+        Ok(Self {
+            world_address,
+            models,
+            writer_candidates,
+            owner_candidates,
+            desired,
+            owned_resources,
+        })
+    }
+
+    async fn ask(&self, provider: &StarknetProvider, function: &str, calldata: Vec<FieldElement>) -> anyhow::Result<bool> {
+        let result = provider.call(
+            FunctionCall {
+                contract_address: self.world_address,
+                entry_point_selector: get_selector_from_name(function)?,
+                calldata,
+            },
+            BlockId::Tag(BlockTag::Pending),
+        ).await.map_err(|error| anyhow!("{} failed: {}", function, error))?;
+
+        Ok(result.first().is_some_and(|answer| *answer != FieldElement::ZERO))
+    }
+
+    async fn owners(&self, provider: &StarknetProvider, resource_id: FieldElement) -> anyhow::Result<Vec<Grantee>> {
+        let mut owners = vec![];
+        for candidate in &self.owner_candidates {
+            if self.ask(provider, "is_owner", vec![candidate.address, resource_id]).await? {
+                owners.push(candidate.clone());
+            }
+        }
+        Ok(owners)
+    }
+
+    /**
+     * asks the world who writes and owns each model, and compares it with the desired grants
+     */
+    async fn report(&self, state: &ServerState) -> anyhow::Result<AuthReport> {
+        let provider = chain::provider(state);
+        let mut namespaces: BTreeMap<String, Vec<ModelPermissions>> = BTreeMap::new();
+        let mut current = BTreeSet::new();
+
+        let world_owners = self.owners(&provider, FieldElement::ZERO).await?;
+        current.extend(world_owners.iter().map(|grantee| Grant {
+            role: Role::Owner,
+            resource: "world".to_string(),
+            resource_id: FieldElement::ZERO,
+            grantee: grantee.clone(),
+        }));
+
+        for (model, namespace, resource_id) in &self.models {
+            let mut writers = vec![];
+            for candidate in &self.writer_candidates {
+                if self.ask(&provider, "is_writer", vec![*resource_id, candidate.address]).await? {
+                    writers.push(candidate.clone());
+                }
+            }
+            let owners = self.owners(&provider, *resource_id).await?;
+
+            let grants = |role, grantees: &Vec<Grantee>| grantees.iter()
+                .map(|grantee| Grant { role, resource: model.clone(), resource_id: *resource_id, grantee: grantee.clone() })
+                .collect::<Vec<_>>();
+            current.extend(grants(Role::Writer, &writers));
+            current.extend(grants(Role::Owner, &owners));
+
+            namespaces.entry(namespace.clone()).or_default().push(ModelPermissions {
+                model: model.clone(),
+                resource_id: *resource_id,
+                writers,
+                owners,
+            });
+        }
+
+        // contracts aren't listed, only checked when the config names their owners
+        for grant in self.desired.iter().filter(|grant| grant.role == Role::Owner) {
+            let is_listed = grant.resource_id == FieldElement::ZERO
+                || self.models.iter().any(|(_, _, resource_id)| *resource_id == grant.resource_id);
+            if !is_listed && self.ask(&provider, "is_owner", vec![grant.grantee.address, grant.resource_id]).await? {
+                current.insert(grant.clone());
+            }
+        }
+
+        let same = |a: &Grant, b: &Grant| a.role == b.role && a.resource_id == b.resource_id && a.grantee.address == b.grantee.address;
+
+        let missing = self.desired.iter()
+            .filter(|grant| !current.iter().any(|granted| same(grant, granted)))
+            .cloned()
+            .collect();
+        let unexpected = current.iter()
+            .filter(|granted| granted.role == Role::Writer || self.owned_resources.contains(&granted.resource_id))
+            .filter(|granted| !self.desired.iter().any(|grant| same(grant, granted)))
+            .cloned()
+            .collect();
+
+        Ok(AuthReport {
+            world_address: self.world_address,
+            world_owners,
+            namespaces: namespaces.into_iter()
+                .map(|(namespace, models)| NamespacePermissions { namespace, models })
+                .collect(),
+            drift: Drift { missing, unexpected },
+        })
+    }
+}
+
+fn grant_call(world_address: FieldElement, grant: &Grant, revoke: bool) -> anyhow::Result<Call> {
+    let (function, calldata) = match (grant.role, revoke) {
+        (Role::Writer, false) => ("grant_writer", vec![grant.resource_id, grant.grantee.address]),
+        (Role::Writer, true) => ("revoke_writer", vec![grant.resource_id, grant.grantee.address]),
+        (Role::Owner, false) => ("grant_owner", vec![grant.grantee.address, grant.resource_id]),
+        (Role::Owner, true) => ("revoke_owner", vec![grant.grantee.address, grant.resource_id]),
+    };
+
+    Ok(Call {
+        to: world_address,
+        selector: get_selector_from_name(function)?,
+        calldata,
+    })
+}
+
+/**
+ * who writes and owns the world's models, and how that differs from the manifests and the config
+ */
+pub async fn report(state: &ServerState) -> ApiResult<AuthReport> {
+    let resolved = Resolved::new(state, &state.config.auth())
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+
+    Ok(resolved.report(state).await?)
+}
+
+/**
+ * the report, and who owns each of the resources out of the dev accounts and the owners the config names.
+ * The manifest and the config are resolved once for both
+ */
+pub async fn report_with_owners(state: &ServerState, resource_ids: &[FieldElement]) -> ApiResult<(AuthReport, Vec<Vec<Grantee>>)> {
+    let resolved = Resolved::new(state, &state.config.auth())
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let provider = chain::provider(state);
+
+    let mut owners = vec![];
+    for resource_id in resource_ids {
+        owners.push(resolved.owners(&provider, *resource_id).await?);
+    }
+    Ok((resolved.report(state).await?, owners))
+}
+
+/**
+ * grants what is missing in one transaction, and revokes what isn't asked for when `revoke` is set.
+ * The account has to own the world or the resources, the first dev account migrated the world by default.
+ */
+pub async fn apply(state: &ServerState, account: Option<FieldElement>, revoke: bool) -> ApiResult<Applied> {
+    let resolved = Resolved::new(state, &state.config.auth())
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let before = resolved.report(state).await?;
+
+    let granted = before.drift.missing;
+    let revoked = if revoke { before.drift.unexpected } else { vec![] };
+
+    let mut calls = vec![];
+    for grant in &granted {
+        calls.push(grant_call(resolved.world_address, grant, false)?);
+    }
+    for grant in &revoked {
+        calls.push(grant_call(resolved.world_address, grant, true)?);
+    }
+
+    let mut transaction_hash = None;
+    if !calls.is_empty() {
+        let signer = chain::dev_signer(state, account).await?;
+        let result = signer.execute(calls).send().await
+            .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+        chain::wait_for_receipt(signer.provider(), result.transaction_hash).await
+            .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+        transaction_hash = Some(result.transaction_hash);
+    }
+
+    Ok(Applied {
+        transaction_hash,
+        granted,
+        revoked,
+        report: resolved.report(state).await?,
+    })
+}
+
+/**
+ * the post-migration hook: applies the grants whenever sozo writes a new deployment manifest
+ */
+pub async fn watch_migrations(state: ServerState) {
+    let mut last_migration: Option<SystemTime> = None;
+    let mut last_error: Option<String> = None;
+
+    loop {
+        let migration = Manifest::path(&state.manifest_base_dir)
+            .and_then(|path| path.metadata().ok())
+            .and_then(|metadata| metadata.modified().ok());
+
+        if migration.is_some() && migration != last_migration {
+            // only marked as handled once applied, a failure is retried on the next poll
+            match apply(&state, None, false).await {
+                Ok(applied) => {
+                    last_migration = migration;
+                    last_error = None;
+                    if applied.granted.is_empty() {
+                        println!("Authorizations are up to date");
+                    } else {
+                        println!("Granted {} authorizations", applied.granted.len());
+                    }
+                }
+                // printed once, not on every retry
+                Err(error) if last_error.as_deref() != Some(error.message()) => {
+                    eprintln!("Failed to apply the authorizations: {}", error.message());
+                    last_error = Some(error.message().to_string());
+                }
+                Err(_) => {}
+            }
+        }
+
+        tokio::time::sleep(MIGRATION_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+use anyhow::{anyhow, bail};
+use jsonrpsee_core::client::ClientT;
+use jsonrpsee_core::params::ArrayParams;
+use starknet::accounts::{ExecutionEncoding, SingleOwnerAccount};
+use starknet::core::types::{BlockId, BlockTag, ExecutionResult, FieldElement, MaybePendingTransactionReceipt, StarknetError};
+use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
+use starknet::providers::{Provider, ProviderError};
+use starknet::signers::{LocalWallet, SigningKey};
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::Genesis;
+use crate::server_state::ServerState;
+
+/// How long a transaction may take to get a receipt, Katana mines instantly by default.
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub type StarknetProvider = JsonRpcClient<HttpTransport>;
+
+pub type DevSigner = SingleOwnerAccount<StarknetProvider, LocalWallet>;
+
+pub fn provider(state: &ServerState) -> StarknetProvider {
+    JsonRpcClient::new(HttpTransport::new(state.rpc_url.clone()))
+}
+
+/**
+ * a genesis dev account that signs against the pending block, the first one when no address is given
+ */
+pub async fn dev_signer(state: &ServerState, address: Option<FieldElement>) -> ApiResult<DevSigner> {
+    let genesis = Genesis::read(&state.genesis_path())?;
+    let accounts = genesis.dev_accounts();
+
+    let account = match address {
+        Some(address) => accounts.into_iter().find(|account| account.address == address)
+            .ok_or_else(|| ApiError::not_found(format!("{:#x} is not a dev account", address)))?,
+        None => accounts.into_iter().next()
+            .ok_or_else(|| ApiError::not_found("the genesis has no dev accounts"))?,
+    };
+
+    let provider = provider(state);
+    let chain_id = provider.chain_id().await?;
+
+    let mut signer = SingleOwnerAccount::new(
+        provider,
+        LocalWallet::from_signing_key(SigningKey::from_secret_scalar(account.private_key)),
+        account.address,
+        chain_id,
+        ExecutionEncoding::New,
+    );
+    // with mining on demand, the nonce of the latest block is stale
+    signer.set_block_id(BlockId::Tag(BlockTag::Pending));
+
+    Ok(signer)
+}
+
+/**
+ * writes a storage slot directly, without a transaction
+ */
+pub async fn set_storage_at(state: &ServerState, contract_address: FieldElement, key: FieldElement, value: FieldElement) -> anyhow::Result<()> {
+    let mut params = ArrayParams::new();
+    params.insert(format!("{:#x}", contract_address))?;
+    params.insert(format!("{:#x}", key))?;
+    params.insert(format!("{:#x}", value))?;
+
+    state.json_rpc_client.request::<(), ArrayParams>("katana_setStorageAt", params).await?;
+    Ok(())
+}
+
+/**
+ * waits until the transaction has a receipt, fails when it reverted
+ */
+pub async fn wait_for_receipt(provider: &StarknetProvider, transaction_hash: FieldElement) -> anyhow::Result<MaybePendingTransactionReceipt> {
+    let receipt = tokio::time::timeout(RECEIPT_TIMEOUT, async {
+        loop {
+            match provider.get_transaction_receipt(transaction_hash).await {
+                Ok(receipt) => return Ok(receipt),
+                Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                Err(error) => return Err(anyhow!(error))
+            }
+        }
+    }).await
+        .map_err(|_| anyhow!("no receipt for {:#x} within {:?}", transaction_hash, RECEIPT_TIMEOUT))??;
+
+    if let ExecutionResult::Reverted { reason } = receipt.execution_result() {
+        bail!("transaction {:#x} reverted: {}", transaction_hash, reason);
+    }
+
+    Ok(receipt)
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use starknet::core::types::FieldElement;
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::storage::model;
+use crate::manifest::Manifest;
+use crate::model_storage::ModelLayout;
+use crate::server_state::ServerState;
+use crate::torii::{self, EntityQuery};
+
+/// Entities sampled per model when the check doesn't say.
+const DEFAULT_SAMPLE: u32 = 100;
+
+/// Entities asked from Torii at once while sampling.
+const SAMPLE_PAGE_SIZE: u32 = 100;
+
+#[derive(Deserialize, Default)]
+pub struct ConsistencyCheck {
+    /// all the models in the manifest by default
+    pub models: Option<Vec<String>>,
+    /// how many of each model's entities to sample from Torii
+    pub sample: Option<u32>,
+    /// entities that should exist, they're looked up on both sides
+    #[serde(default)]
+    pub entities: Vec<KnownEntity>,
+}
+
+#[derive(Deserialize)]
+pub struct KnownEntity {
+    pub model: String,
+    /// by member name
+    pub keys: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+pub struct ConsistencyReport {
+    pub consistent: bool,
+    pub models: Vec<ModelConsistency>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ModelConsistency {
+    pub model: String,
+    /// how many entities were compared
+    pub checked: usize,
+    pub matching: usize,
+    pub mismatches: Vec<Mismatch>,
+    /// stored in the world, but not indexed by Torii
+    pub missing_in_torii: Vec<Map<String, Value>>,
+    /// known entities Torii doesn't have whose values are all zero in the world. A model that was never set
+    /// reads the same, so whether they exist can't be told
+    pub zero_valued: Vec<Map<String, Value>>,
+}
+
+#[derive(Serialize)]
+pub struct Mismatch {
+    pub entity_id: Option<String>,
+    pub keys: Map<String, Value>,
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Serialize)]
+pub struct FieldDiff {
+    /// the member, nested members are compared as a whole
+    pub field: String,
+    pub torii: Value,
+    pub chain: Value,
+}
+
+/// One model's layout, and what was read of it from the chain.
+struct ChainModel {
+    name: String,
+    layout: ModelLayout,
+    world_address: FieldElement,
+}
+
+impl ChainModel {
+    fn key_names(&self) -> Vec<String> {
+        self.layout.members.iter().filter(|member| member.key).map(|member| member.name.clone()).collect()
+    }
+
+    /// the slots stored for the keys, all zero for an entity that was never set
+    async fn read(&self, state: &ServerState, keys: &Map<String, Value>) -> ApiResult<Vec<FieldElement>> {
+        let storage = self.layout.entity(&Value::Object(keys.clone()))
+            .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+        model::read(state, self.world_address, &storage).await
+    }
+}
+
+impl ModelConsistency {
+    fn compare(&mut self, layout: &ModelLayout, entity_id: Option<String>, keys: Map<String, Value>, torii: &Map<String, Value>, slots: &[FieldElement]) {
+        self.checked += 1;
+
+        let torii = torii::unflatten(torii);
+        let chain = match layout.decode(slots) {
+            Ok(Value::Object(chain)) => chain,
+            _ => Map::new(),
+        };
+
+        let fields: Vec<FieldDiff> = layout.members.iter()
+            .filter(|member| !member.key)
+            .filter_map(|member| {
+                let torii_value = torii.get(&member.name).cloned().unwrap_or(Value::Null);
+                let expected = Map::from_iter([(member.name.clone(), torii_value.clone())]);
+                // compared once serialized, Torii's padded hex and the decoded values are the same felts.
+                // A value that doesn't even encode differs
+                let differs = layout.mismatches(slots, &expected).map_or(true, |mismatches| !mismatches.is_empty());
+
+                differs.then(|| FieldDiff {
+                    field: member.name.clone(),
+                    torii: torii_value,
+                    chain: chain.get(&member.name).cloned().unwrap_or(Value::Null),
+                })
+            })
+            .collect();
+
+        if fields.is_empty() {
+            self.matching += 1;
+        } else {
+            self.mismatches.push(Mismatch { entity_id, keys, fields });
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty() && self.missing_in_torii.is_empty()
+    }
+}
+
+async fn check_model(state: &ServerState, chain_model: &ChainModel, sample: u32, known: &[&KnownEntity]) -> ApiResult<ModelConsistency> {
+    let mut consistency = ModelConsistency { model: chain_model.name.clone(), ..Default::default() };
+    let key_names = chain_model.key_names();
+    let keys_of = |values: &Map<String, Value>| -> Map<String, Value> {
+        key_names.iter().filter_map(|name| values.get(name).map(|value| (name.clone(), value.clone()))).collect()
+    };
+
+    let mut after = None;
+    let mut sampled = 0;
+    while sampled < sample {
+        let page = torii::entities(state, &EntityQuery {
+            model: chain_model.name.clone(),
+            keys: Map::new(),
+            first: Some(SAMPLE_PAGE_SIZE.min(sample - sampled)),
+            after: after.take(),
+        }).await?;
+
+        // Torii has them, so they exist, even when all their values are zero
+        for row in &page.rows {
+            let keys = keys_of(&row.values);
+            let slots = chain_model.read(state, &keys).await?;
+            consistency.compare(&chain_model.layout, row.entity_id.clone(), keys, &row.values, &slots);
+        }
+
+        sampled += page.rows.len() as u32;
+        match page.next_cursor {
+            Some(cursor) if !page.rows.is_empty() => after = Some(cursor),
+            _ => break,
+        }
+    }
+
+    for entity in known {
+        let slots = chain_model.read(state, &entity.keys).await?;
+        let page = torii::entities(state, &EntityQuery {
+            model: chain_model.name.clone(),
+            keys: entity.keys.clone(),
+            first: Some(1),
+            after: None,
+        }).await?;
+
+        match page.rows.first() {
+            Some(row) => consistency.compare(&chain_model.layout, row.entity_id.clone(), entity.keys.clone(), &row.values, &slots),
+            None if slots.iter().all(|slot| *slot == FieldElement::ZERO) => consistency.zero_valued.push(entity.keys.clone()),
+            None => consistency.missing_in_torii.push(entity.keys.clone()),
+        }
+    }
+
+    Ok(consistency)
+}
+
+/**
+ * compares what Torii indexed with what is stored in the world, model by model
+ */
+pub async fn check(state: &ServerState, check: &ConsistencyCheck) -> ApiResult<ConsistencyReport> {
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let world_address = model::world_address(state)?;
+
+    // only the known entities' models when models aren't asked for
+    let mut names: Vec<String> = match (&check.models, check.entities.is_empty()) {
+        (Some(models), _) => models.clone(),
+        (None, true) => manifest.models.iter().map(|model| model.name.clone()).collect(),
+        (None, false) => vec![],
+    };
+    for entity in &check.entities {
+        names.push(entity.model.clone());
+    }
+
+    let mut models: Vec<ModelConsistency> = vec![];
+    for name in names {
+        let (name, layout) = model::layout(state, &name)?;
+        if models.iter().any(|checked| checked.model == name) {
+            continue;
+        }
+
+        let chain_model = ChainModel { name, layout, world_address };
+        let known: Vec<&KnownEntity> = check.entities.iter()
+            .filter(|entity| manifest.find_model(&entity.model).is_some_and(|model| model.name == chain_model.name))
+            .collect();
+
+        let sample = match (&check.models, check.entities.is_empty()) {
+            (None, false) => 0,
+            _ => check.sample.unwrap_or(DEFAULT_SAMPLE),
+        };
+
+        models.push(check_model(state, &chain_model, sample, &known).await?);
+    }
+
+    Ok(ConsistencyReport {
+        consistent: models.iter().all(ModelConsistency::is_consistent),
+        models,
+    })
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use anyhow::Context;
+use cairo_lang_sierra_to_casm::metadata::{calc_metadata, MetadataComputationConfig};
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
+use cairo_lang_starknet_classes::contract_class::{ContractClass, ContractEntryPoint};
+use serde::Serialize;
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::contract::SierraClass;
+use starknet::core::types::{FieldElement, FlattenedSierraClass};
+
+/// The most felts of bytecode Starknet takes in a declared class, the "Max contract bytecode size"
+/// on the limits page of the Starknet docs (docs.starknet.io/tools/limits-and-triggers).
+pub const MAX_BYTECODE_SIZE: usize = 81_290;
+
+/// A Sierra class with everything needed to declare it.
+pub struct CompiledClass {
+    pub contract_class: ContractClass,
+    pub sierra_class: FlattenedSierraClass,
+    pub casm_class: CasmContractClass,
+    pub class_hash: FieldElement,
+    pub compiled_class_hash: FieldElement,
+}
+
+/// What calling an entry point costs at most, leaving out loops and recursion, which pay for
+/// themselves with `withdraw_gas` at runtime.
+#[serde_as]
+#[derive(Serialize)]
+pub struct EntryPointCost {
+    #[serde(rename = "type")]
+    pub entry_point_type: &'static str,
+    #[serde_as(as = "UfeHex")]
+    pub selector: FieldElement,
+    pub function: String,
+    /// gas per cost token: const (the gas the steps, holes and range checks add up to), step,
+    /// hole, range_check and the builtins
+    pub costs: BTreeMap<String, i64>,
+    pub ap_change: Option<usize>,
+}
+
+impl CompiledClass {
+    pub fn bytecode_size(&self) -> usize {
+        self.casm_class.bytecode.len()
+    }
+
+    /**
+     * the costs the Sierra to CASM compiler computes for every entry point
+     */
+    pub fn entry_point_costs(&self) -> anyhow::Result<Vec<EntryPointCost>> {
+        let program = self.contract_class.extract_sierra_program()
+            .context("failed to decode the Sierra program")?;
+
+        let config = |linear| MetadataComputationConfig {
+            linear_gas_solver: linear,
+            linear_ap_change_solver: linear,
+            compute_runtime_costs: true,
+            ..Default::default()
+        };
+        // classes compiled before Sierra 1.4 need the equation solver
+        let metadata = calc_metadata(&program, config(true))
+            .or_else(|_| calc_metadata(&program, config(false)))
+            .context("failed to compute the costs")?;
+
+        let entry_points = &self.contract_class.entry_points_by_type;
+        let typed = |entry_point_type, entry_points: &Vec<ContractEntryPoint>| entry_points.iter()
+            .map(move |entry_point| (entry_point_type, entry_point.clone()))
+            .collect::<Vec<_>>();
+
+        [
+            typed("constructor", &entry_points.constructor),
+            typed("external", &entry_points.external),
+            typed("l1_handler", &entry_points.l1_handler),
+        ].concat().into_iter()
+            .map(|(entry_point_type, entry_point)| {
+                let function = &program.funcs.get(entry_point.function_idx)
+                    .with_context(|| format!("entry point points at unknown function {}", entry_point.function_idx))?
+                    .id;
+
+                let costs = metadata.gas_info.function_costs.get(function)
+                    .map(|costs| costs.iter().map(|(token, cost)| (token.name(), *cost)).collect())
+                    .unwrap_or_default();
+
+                Ok(EntryPointCost {
+                    entry_point_type,
+                    selector: FieldElement::from_byte_slice_be(&entry_point.selector.to_bytes_be())
+                        .context("selector is not a felt")?,
+                    function: function.to_string(),
+                    costs,
+                    ap_change: metadata.ap_change_info.function_ap_change.get(function).copied(),
+                })
+            })
+            .collect()
+    }
+}
+
+/**
+ * Compiles a Sierra contract class (the `.contract_class.json` Scarb writes) to CASM
+ * and computes both of its hashes.
+ */
+pub fn compile(contract_class: Value) -> anyhow::Result<CompiledClass> {
+    let sierra: SierraClass = serde_json::from_value(contract_class.clone())
+        .context("not a Sierra contract class")?;
+    let class_hash = sierra.class_hash()
+        .context("failed to compute the class hash")?;
+
+    let cairo_class: ContractClass = serde_json::from_value(contract_class)
+        .context("not a Sierra contract class")?;
+    // starknet-rs doesn't hash segmented bytecode like the sequencer does, cairo-lang does
+    let casm_class = CasmContractClass::from_contract_class(cairo_class.clone(), false, usize::MAX)
+        .context("failed to compile to CASM")?;
+    let compiled_class_hash = FieldElement::from_byte_slice_be(&casm_class.compiled_class_hash().to_bytes_be())
+        .context("compiled class hash is not a felt")?;
+
+    Ok(CompiledClass {
+        contract_class: cairo_class,
+        sierra_class: sierra.flatten().context("failed to flatten the class")?,
+        casm_class,
+        class_hash,
+        compiled_class_hash,
+    })
+}
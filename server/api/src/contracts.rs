@@ -0,0 +1,74 @@
+use starknet::core::types::{BlockId, BlockTag, ContractClass, FieldElement};
+use starknet::providers::Provider;
+use crate::abi::Abi;
+use crate::chain;
+use crate::error::{ApiError, ApiResult};
+use crate::manifest::{self, Manifest};
+use crate::server_state::ServerState;
+
+/// A deployed contract, with the ABI its arguments are encoded against.
+pub struct Target {
+    /// None when it isn't in the manifest
+    pub name: Option<String>,
+    pub address: FieldElement,
+    pub abi: Abi,
+}
+
+/**
+ * finds a contract by name or address in the manifest, or asks Katana for the ABI of any other address
+ */
+pub async fn resolve(state: &ServerState, contract: &str) -> ApiResult<Target> {
+    // contracts outside the world can be reached without a manifest
+    let manifest = Manifest::load(&state.manifest_base_dir);
+
+    if let Ok(manifest) = &manifest {
+        if let Some(found) = manifest.find_contract(contract).map_err(ApiError::bad_request)? {
+            let address = found.address
+                .ok_or_else(|| ApiError::conflict(format!("{} isn't deployed", found.name)))?;
+
+            return Ok(Target {
+                name: Some(found.name.clone()),
+                address,
+                abi: manifest.abi(found)?,
+            });
+        }
+    }
+
+    let address = manifest::literal_address(contract).ok_or_else(|| not_found(&manifest, contract))?;
+
+    let class = chain::provider(state).get_class_at(BlockId::Tag(BlockTag::Pending), address).await
+        .map_err(|_| ApiError::not_found(format!("no contract at {:#x}", address)))?;
+
+    let abi = match class {
+        ContractClass::Sierra(class) => Abi::parse(serde_json::from_str(&class.abi)?)?,
+        ContractClass::Legacy(_) => return Err(ApiError::bad_request("Cairo 0 contracts aren't supported")),
+    };
+
+    Ok(Target {
+        name: None,
+        address,
+        abi,
+    })
+}
+
+/**
+ * the address of a contract in the manifest, or the 0x prefixed address itself
+ */
+pub fn resolve_address(state: &ServerState, contract: &str) -> ApiResult<FieldElement> {
+    let manifest = Manifest::load(&state.manifest_base_dir);
+
+    if let Ok(manifest) = &manifest {
+        if let Some(found) = manifest.find_contract(contract).map_err(ApiError::bad_request)? {
+            return found.address.ok_or_else(|| ApiError::conflict(format!("{} isn't deployed", found.name)));
+        }
+    }
+
+    manifest::literal_address(contract).ok_or_else(|| not_found(&manifest, contract))
+}
+
+fn not_found(manifest: &anyhow::Result<Manifest>, contract: &str) -> ApiError {
+    match manifest {
+        Ok(_) => ApiError::not_found(format!("no contract `{}` in the manifest", contract)),
+        Err(error) => ApiError::not_found(format!("{:#}", error)),
+    }
+}
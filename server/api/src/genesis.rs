@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+
+/// Katana's fee token, when the genesis doesn't name another one.
+pub const ETH_ADDRESS: &str = "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7";
+
+/// Where STRK is deployed on Starknet, and in genesis files that deploy it.
+pub const STRK_ADDRESS: &str = "0x4718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d";
+
+/// Katana's Universal Deployer Contract, when the genesis doesn't name another one.
+pub const UDC_ADDRESS: &str = "0x41a78e741e5af2fec34b695679bc6891742439f7afb8484ecd7766661ad02bf";
+
+/// The balances of Katana's fee token and of OpenZeppelin's ERC20 live in this mapping.
+pub const BALANCES_VARIABLE: &str = "ERC20_balances";
+
+/// Their total supply, a u256 like the balances.
+pub const TOTAL_SUPPLY_VARIABLE: &str = "ERC20_total_supply";
+
+/// Katana's genesis.json, only the parts Keiko looks at are typed.
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    #[serde(default)]
+    #[serde_as(as = "BTreeMap<UfeHex, _>")]
+    pub accounts: BTreeMap<FieldElement, GenesisAccount>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "BTreeMap<UfeHex, _>")]
+    pub contracts: BTreeMap<FieldElement, GenesisContract>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<GenesisClass>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisAccount {
+    #[serde_as(as = "UfeHex")]
+    pub public_key: FieldElement,
+    /// only dev accounts have their key in the genesis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub private_key: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub balance: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub nonce: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<BTreeMap<UfeHex, UfeHex>>")]
+    pub storage: Option<BTreeMap<FieldElement, FieldElement>>,
+}
+
+/// A contract deployed in the genesis block, with its storage.
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisContract {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub balance: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub nonce: Option<FieldElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<BTreeMap<UfeHex, UfeHex>>")]
+    pub storage: Option<BTreeMap<FieldElement, FieldElement>>,
+}
+
+/// A class declared in the genesis block.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisClass {
+    /// the path of the class artifact, relative to the genesis file, or the artifact itself
+    pub class: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class_hash: Option<FieldElement>,
+    /// accounts can refer to the class by this name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// A genesis account Keiko can sign for.
+#[derive(Debug, Clone, Copy)]
+pub struct DevAccount {
+    pub address: FieldElement,
+    pub private_key: FieldElement,
+}
+
+impl Genesis {
+    pub fn read(path: &str) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path))?;
+
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path))
+    }
+
+    pub fn write(&self, path: &str) -> anyhow::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path))
+    }
+
+    /**
+     * the address of the token fees are paid in
+     */
+    pub fn fee_token_address(&self) -> Option<FieldElement> {
+        self.other.get("feeToken")?
+            .get("address")?
+            .as_str()
+            .and_then(|address| FieldElement::from_hex_be(address).ok())
+    }
+
+    /**
+     * the address of the Universal Deployer Contract
+     */
+    pub fn universal_deployer_address(&self) -> Option<FieldElement> {
+        self.other.get("universalDeployer")?
+            .get("address")?
+            .as_str()
+            .and_then(|address| FieldElement::from_hex_be(address).ok())
+    }
+
+    /**
+     * the accounts with a private key, in address order
+     */
+    pub fn dev_accounts(&self) -> Vec<DevAccount> {
+        self.accounts.iter()
+            .filter_map(|(address, account)| account.private_key.map(|private_key| DevAccount {
+                address: *address,
+                private_key,
+            }))
+            .collect()
+    }
+}
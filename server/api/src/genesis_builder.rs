@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Context};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::utils::{get_contract_address, get_storage_var_address, starknet_keccak};
+use starknet_crypto::{get_public_key, poseidon_hash_many};
+use crate::genesis::{Genesis, GenesisAccount, GenesisClass, GenesisContract, BALANCES_VARIABLE, ETH_ADDRESS};
+
+/// Katana's OpenZeppelin account class, the dev accounts are deployed from it.
+pub const ACCOUNT_CLASS_HASH: &str = "0x05400e90f7e0ae78bd02c77cd75527280470e2fe19c54970dd79dc37a9d3645c";
+
+/// The order of the Stark curve, private keys are below it.
+const CURVE_ORDER: &str = "0x0800000000000010ffffffffffffffffb781126dcae7b2321e66a241adc64d2f";
+
+/// 1000 tokens of 18 decimals.
+const DEFAULT_BALANCE: &str = "1000000000000000000000";
+
+/// How the genesis is generated, the `[genesis]` section of the config file.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig {
+    /// how many dev accounts to generate
+    pub accounts: u16,
+    /// the same seed gives the same accounts
+    pub seed: String,
+    /// of each dev account, in wei, decimal or hex
+    pub eth_balance: String,
+    /// of each dev account, in wei, needs a `strk_token`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strk_balance: Option<String>,
+    /// Katana only knows its fee token, STRK is a contract deployed in the genesis
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strk_token: Option<TokenConfig>,
+    /// addresses that aren't dev accounts but get tokens anyway
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefunded: Vec<Prefunded>,
+    /// paths are relative to the working directory
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<GenesisClass>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "BTreeMap<UfeHex, _>")]
+    pub contracts: BTreeMap<FieldElement, GenesisContract>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    /// an ERC20 that keeps its balances in `ERC20_balances`, declared in `classes`
+    #[serde_as(as = "UfeHex")]
+    pub class: FieldElement,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Prefunded {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    /// in wei, decimal or hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eth: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strk: Option<String>,
+}
+
+/// A generated dev account.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratedAccount {
+    pub address: FieldElement,
+    pub public_key: FieldElement,
+    pub private_key: FieldElement,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        Self {
+            accounts: 10,
+            seed: "0".to_string(),
+            eth_balance: DEFAULT_BALANCE.to_string(),
+            strk_balance: None,
+            strk_token: None,
+            prefunded: vec![],
+            classes: vec![],
+            contracts: BTreeMap::new(),
+        }
+    }
+}
+
+/// A genesis Katana accepts, for when there is none to start from.
+fn empty_genesis() -> Genesis {
+    let other = json!({
+        "number": 0,
+        "parentHash": "0x0",
+        "timestamp": 0,
+        "stateRoot": "0x0",
+        "sequencerAddress": "0x1",
+        "gasPrices": { "ETH": 100000000000u64, "STRK": 100000000000u64 },
+        "feeToken": { "name": "Ether", "symbol": "ETH", "decimals": 18, "address": ETH_ADDRESS },
+    });
+
+    Genesis {
+        other: match other {
+            Value::Object(other) => other,
+            _ => Map::new(),
+        },
+        ..Default::default()
+    }
+}
+
+/**
+ * a token amount in wei, `1000000000000000000` or `0xde0b6b3a7640000`
+ */
+pub fn parse_amount(amount: &str) -> anyhow::Result<BigUint> {
+    let parsed = match amount.strip_prefix("0x") {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::from_str(amount).ok(),
+    };
+
+    match parsed {
+        Some(amount) if amount.bits() <= 256 => Ok(amount),
+        Some(_) => bail!("`{}` doesn't fit in a u256", amount),
+        None => bail!("`{}` is not an amount in wei", amount),
+    }
+}
+
+fn to_felt(value: &BigUint) -> anyhow::Result<FieldElement> {
+    FieldElement::from_byte_slice_be(&value.to_bytes_be())
+        .map_err(|_| anyhow!("{} is too large for a felt", value))
+}
+
+/// Adds a u256 balance to an ERC20's storage, the high half in the next slot.
+fn add_balance(storage: &mut BTreeMap<FieldElement, FieldElement>, address: FieldElement, amount: &BigUint) -> anyhow::Result<()> {
+    let low_key = get_storage_var_address(BALANCES_VARIABLE, &[address])?;
+    let high_key = low_key + FieldElement::ONE;
+
+    let to_biguint = |felt: Option<&FieldElement>| BigUint::from_bytes_be(&felt.copied().unwrap_or_default().to_bytes_be());
+    let balance = (to_biguint(storage.get(&high_key)) << 128u32) + to_biguint(storage.get(&low_key)) + amount;
+
+    let mask = (BigUint::from(1u8) << 128u32) - 1u8;
+    storage.insert(low_key, to_felt(&(&balance & &mask))?);
+    storage.insert(high_key, to_felt(&(&balance >> 128u32))?);
+    Ok(())
+}
+
+/// Whether Katana created its database in the dir, it only reads the genesis and the chain id then.
+pub fn has_database(db_dir: &Path) -> bool {
+    fs::read_dir(db_dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+impl GenesisConfig {
+    /**
+     * the dev accounts, derived from the seed: the same seed and count always give the same keys.
+     * They aren't the keys `katana --seed` generates.
+     */
+    pub fn dev_accounts(&self) -> anyhow::Result<Vec<GeneratedAccount>> {
+        let class_hash = FieldElement::from_hex_be(ACCOUNT_CLASS_HASH)?;
+        let curve_order = FieldElement::from_hex_be(CURVE_ORDER)?;
+        let seed = starknet_keccak(self.seed.as_bytes());
+
+        let mut accounts = vec![];
+        for index in 0..self.accounts {
+            let mut private_key = poseidon_hash_many(&[seed, FieldElement::from(index)]);
+            // hashed again until it's a valid key, which almost never takes a second round
+            while private_key == FieldElement::ZERO || private_key >= curve_order {
+                private_key = poseidon_hash_many(&[private_key]);
+            }
+
+            let public_key = get_public_key(&private_key);
+            accounts.push(GeneratedAccount {
+                address: get_contract_address(public_key, class_hash, &[public_key], FieldElement::ZERO),
+                public_key,
+                private_key,
+            });
+        }
+
+        Ok(accounts)
+    }
+
+    /**
+     * the genesis, on top of an existing one: its dev accounts are replaced, everything else is kept
+     */
+    pub fn build(&self, base: Option<Genesis>) -> anyhow::Result<Genesis> {
+        let mut genesis = base.unwrap_or_else(empty_genesis);
+
+        let eth_balance = parse_amount(&self.eth_balance)?;
+        let strk_balance = self.strk_balance.as_deref().map(parse_amount).transpose()?;
+
+        genesis.accounts.retain(|_, account| account.private_key.is_none());
+        let accounts = self.dev_accounts()?;
+        for account in &accounts {
+            genesis.accounts.insert(account.address, GenesisAccount {
+                public_key: account.public_key,
+                private_key: Some(account.private_key),
+                balance: Some(to_felt(&eth_balance)?),
+                ..Default::default()
+            });
+        }
+
+        for class in &self.classes {
+            let mut class = class.clone();
+            // the genesis file resolves paths against its own directory
+            if let Value::String(path) = &class.class {
+                let absolute = fs::canonicalize(path).with_context(|| format!("class `{}` not found", path))?;
+                class.class = Value::String(absolute.display().to_string());
+            }
+            // the genesis is built again on every start
+            if !genesis.classes.iter().any(|declared| declared.class == class.class) {
+                genesis.classes.push(class);
+            }
+        }
+
+        for (address, contract) in &self.contracts {
+            genesis.contracts.insert(*address, contract.clone());
+        }
+
+        // ETH of the other addresses is written to the fee token's storage
+        let mut eth_storage = BTreeMap::new();
+        let mut strk_storage = BTreeMap::new();
+        for prefunded in &self.prefunded {
+            if let Some(eth) = &prefunded.eth {
+                add_balance(&mut eth_storage, prefunded.address, &parse_amount(eth)?)?;
+            }
+            if let Some(strk) = &prefunded.strk {
+                add_balance(&mut strk_storage, prefunded.address, &parse_amount(strk)?)?;
+            }
+        }
+        if let Some(strk_balance) = &strk_balance {
+            for account in &accounts {
+                add_balance(&mut strk_storage, account.address, strk_balance)?;
+            }
+        }
+
+        if !eth_storage.is_empty() {
+            let fee_token = genesis.other.entry("feeToken").or_insert_with(|| json!({}));
+            let storage = fee_token.as_object_mut()
+                .ok_or_else(|| anyhow!("the genesis' feeToken is not an object"))?
+                .entry("storage").or_insert_with(|| json!({}));
+            for (key, value) in eth_storage {
+                storage[format!("{:#x}", key)] = Value::String(format!("{:#x}", value));
+            }
+        }
+
+        if !strk_storage.is_empty() {
+            let token = self.strk_token.as_ref()
+                .ok_or_else(|| anyhow!("STRK balances need a `strk_token`"))?;
+            let contract = genesis.contracts.entry(token.address).or_default();
+            contract.class = Some(token.class);
+            contract.storage.get_or_insert_with(BTreeMap::new).extend(strk_storage);
+        }
+
+        Ok(genesis)
+    }
+
+    /**
+     * builds the genesis at the path, on top of the one that is there. Katana only reads the genesis when
+     * it creates the database in `db_dir`, after that the genesis is kept and has to match what is generated
+     */
+    pub fn write(&self, path: &str, db_dir: &Path) -> anyhow::Result<Genesis> {
+        let base = match fs::metadata(path) {
+            Ok(_) => Some(Genesis::read(path)?),
+            Err(_) => None,
+        };
+
+        let genesis = self.build(base.clone())?;
+
+        if has_database(db_dir) {
+            let unchanged = match &base {
+                Some(base) => serde_json::to_value(base)? == serde_json::to_value(&genesis)?,
+                None => false,
+            };
+            if !unchanged {
+                bail!(
+                    "the Katana database in {} was created with another genesis, remove it or go back to the genesis options it was created with",
+                    db_dir.display()
+                );
+            }
+            return Ok(genesis);
+        }
+
+        genesis.write(path)?;
+        Ok(genesis)
+    }
+}
@@ -0,0 +1,26 @@
+use axum::{Extension, Json};
+use serde::Deserialize;
+use starknet::core::types::FieldElement;
+use crate::auth::{self, Applied};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+
+#[derive(Deserialize, Default)]
+pub struct Apply {
+    /// the dev account that owns the world, the first one in the genesis by default
+    account: Option<FieldElement>,
+    /// also revoke the grants nobody asked for
+    #[serde(default)]
+    revoke: bool,
+}
+
+/**
+ * grants the writers and owners the manifests and the `[auth]` section ask for
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    payload: Option<Json<Apply>>,
+) -> ApiResult<Json<Applied>> {
+    let Json(payload) = payload.unwrap_or_default();
+    Ok(Json(auth::apply(&state, payload.account, payload.revoke).await?))
+}
@@ -0,0 +1,13 @@
+use axum::{Extension, Json};
+use crate::auth::{self, AuthReport};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+
+/**
+ * the writers and owners of every model per namespace, and the drift from the desired grants
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+) -> ApiResult<Json<AuthReport>> {
+    Ok(Json(auth::report(&state).await?))
+}
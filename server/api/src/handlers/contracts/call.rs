@@ -0,0 +1,61 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
+use starknet::providers::Provider;
+use crate::chain;
+use crate::contracts;
+use crate::error::{ApiError, ApiResult};
+use crate::server_state::ServerState;
+
+#[derive(Deserialize)]
+pub struct Call {
+    /// name (`actions`, `dojo_examples::actions::actions`) or address
+    contract: String,
+    function: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Called {
+    #[serde_as(as = "UfeHex")]
+    contract_address: FieldElement,
+    result: Value,
+    #[serde_as(as = "Vec<UfeHex>")]
+    raw: Vec<FieldElement>,
+}
+
+/**
+ * calls a function against the pending block, nothing is sent
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Call>,
+) -> ApiResult<Json<Called>> {
+    let target = contracts::resolve(&state, &payload.contract).await?;
+    let function = target.abi.function(&payload.function)
+        .ok_or_else(|| ApiError::not_found(format!("no function `{}` in the ABI", payload.function)))?;
+
+    let calldata = target.abi.encode_inputs(function, &payload.args)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    let raw = chain::provider(&state).call(
+        FunctionCall {
+            contract_address: target.address,
+            entry_point_selector: get_selector_from_name(&function.name)?,
+            calldata,
+        },
+        BlockId::Tag(BlockTag::Pending),
+    ).await.map_err(|error| ApiError::bad_request(format!("call failed: {}", error)))?;
+
+    Ok(Json(Called {
+        contract_address: target.address,
+        result: target.abi.decode_outputs(function, &raw)?,
+        raw,
+    }))
+}
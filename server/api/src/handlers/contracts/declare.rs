@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::accounts::{Account, ConnectedAccount};
+use starknet::contract::ContractFactory;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement};
+use starknet::providers::Provider;
+use crate::chain;
+use crate::contract_class::{self, MAX_BYTECODE_SIZE};
+use crate::error::{ApiError, ApiResult};
+use crate::server_state::ServerState;
+
+#[derive(Deserialize)]
+pub struct Declare {
+    /// the Sierra class, as Scarb writes it to `target/dev/*.contract_class.json`
+    contract_class: Value,
+    /// the dev account paying for it, the first one in the genesis by default
+    account: Option<FieldElement>,
+    deploy: Option<Deploy>,
+}
+
+#[derive(Deserialize)]
+pub struct Deploy {
+    #[serde(default)]
+    constructor_calldata: Vec<FieldElement>,
+    #[serde(default)]
+    salt: FieldElement,
+    /// mixes the deployer's address into the contract address
+    #[serde(default)]
+    unique: bool,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Declared {
+    #[serde_as(as = "UfeHex")]
+    class_hash: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    compiled_class_hash: FieldElement,
+    /// None when the class was declared before
+    #[serde_as(as = "Option<UfeHex>")]
+    transaction_hash: Option<FieldElement>,
+    deployment: Option<Deployed>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Deployed {
+    #[serde_as(as = "UfeHex")]
+    contract_address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    transaction_hash: FieldElement,
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Declare>,
+) -> ApiResult<Json<Declared>> {
+    // compiling a large class takes a while, off the runtime's threads
+    let compiled = tokio::task::spawn_blocking(move || contract_class::compile(payload.contract_class)).await?
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    if compiled.bytecode_size() > MAX_BYTECODE_SIZE {
+        return Err(ApiError::bad_request(format!(
+            "the CASM bytecode has {} felts, Starknet accepts at most {}", compiled.bytecode_size(), MAX_BYTECODE_SIZE
+        )));
+    }
+
+    let account = chain::dev_signer(&state, payload.account).await?;
+    let provider = account.provider();
+
+    let is_declared = provider.get_class(BlockId::Tag(BlockTag::Pending), compiled.class_hash).await.is_ok();
+
+    let transaction_hash = if is_declared {
+        None
+    } else {
+        let declaration = account.declare(Arc::new(compiled.sierra_class), compiled.compiled_class_hash)
+            .send().await
+            .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+        chain::wait_for_receipt(provider, declaration.transaction_hash).await
+            .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+        Some(declaration.transaction_hash)
+    };
+
+    let deployment = match payload.deploy {
+        Some(deploy) => {
+            let factory = ContractFactory::new(compiled.class_hash, &account);
+            let deployment = factory.deploy(deploy.constructor_calldata, deploy.salt, deploy.unique);
+
+            let contract_address = deployment.deployed_address();
+            let result = deployment.send().await
+                .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+            chain::wait_for_receipt(provider, result.transaction_hash).await
+                .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+            Some(Deployed {
+                contract_address,
+                transaction_hash: result.transaction_hash,
+            })
+        }
+        None => None
+    };
+
+    Ok(Json(Declared {
+        class_hash: compiled.class_hash,
+        compiled_class_hash: compiled.compiled_class_hash,
+        transaction_hash,
+        deployment,
+    }))
+}
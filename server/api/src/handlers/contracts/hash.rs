@@ -0,0 +1,49 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use crate::contract_class::{self, EntryPointCost, MAX_BYTECODE_SIZE};
+use crate::error::{ApiError, ApiResult};
+
+#[derive(Deserialize)]
+pub struct Hash {
+    contract_class: Value,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Hashes {
+    #[serde_as(as = "UfeHex")]
+    class_hash: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    compiled_class_hash: FieldElement,
+    contract_class_version: String,
+    /// in felts
+    bytecode_size: usize,
+    max_bytecode_size: usize,
+    entry_points: Vec<EntryPointCost>,
+}
+
+/**
+ * hashes and costs of a class, without declaring it or talking to Katana
+ */
+pub async fn handler(Json(payload): Json<Hash>) -> ApiResult<Json<Hashes>> {
+    // compiling and the cost metadata are both CPU bound
+    let (compiled, entry_points) = tokio::task::spawn_blocking(move || {
+        let compiled = contract_class::compile(payload.contract_class)?;
+        let entry_points = compiled.entry_point_costs()?;
+        anyhow::Ok((compiled, entry_points))
+    }).await?
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    Ok(Json(Hashes {
+        class_hash: compiled.class_hash,
+        compiled_class_hash: compiled.compiled_class_hash,
+        contract_class_version: compiled.contract_class.contract_class_version.clone(),
+        bytecode_size: compiled.bytecode_size(),
+        max_bytecode_size: MAX_BYTECODE_SIZE,
+        entry_points,
+    }))
+}
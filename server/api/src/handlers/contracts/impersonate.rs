@@ -0,0 +1,139 @@
+use std::fs;
+use axum::{Extension, Json};
+use serde::Deserialize;
+use serde_json::Value;
+use starknet::core::types::{
+    BlockId, BlockTag, BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1, BroadcastedTransaction,
+    FieldElement, SimulationFlagForEstimateFee,
+};
+use starknet::core::utils::get_selector_from_name;
+use starknet::providers::Provider;
+use crate::chain;
+use crate::contracts;
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::contracts::invoke::Invoked;
+use crate::server_state::ServerState;
+
+/// Katana skips `__validate__`, so any signature does.
+const DUMMY_SIGNATURE: [FieldElement; 2] = [FieldElement::ONE, FieldElement::ONE];
+
+#[derive(Deserialize)]
+pub struct Impersonate {
+    /// the account the transaction is sent from, without its key
+    pub address: FieldElement,
+    /// name (`actions`, `dojo_examples::actions::actions`) or address
+    pub contract: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Value,
+    /// estimated when not given
+    pub max_fee: Option<FieldElement>,
+}
+
+/**
+ * the accounts transactions were sent from by impersonating them
+ */
+pub fn impersonated(state: &ServerState) -> ApiResult<Vec<FieldElement>> {
+    match fs::read_to_string(state.impersonated_path()) {
+        Ok(contents) => Ok(serde_json::from_str::<Vec<String>>(&contents)?
+            .iter()
+            .filter_map(|address| FieldElement::from_hex_be(address).ok())
+            .collect()),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+fn remember(state: &ServerState, address: FieldElement) -> ApiResult<()> {
+    let _lock = state.accounts_file_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut addresses = impersonated(state)?;
+    if !addresses.contains(&address) {
+        addresses.push(address);
+        let addresses: Vec<String> = addresses.iter().map(|address| format!("{:#x}", address)).collect();
+        fs::write(state.impersonated_path(), serde_json::to_string_pretty(&addresses)?)?;
+    }
+    Ok(())
+}
+
+/**
+ * sends a transaction as any deployed account, only works while Katana doesn't validate transactions
+ */
+pub async fn impersonate(state: &ServerState, payload: &Impersonate) -> ApiResult<Invoked> {
+    let node_config = state.config.node_config();
+
+    // Keiko only knows the options of the Katana it runs
+    if !node_config.local_katana {
+        return Err(ApiError::conflict(
+            "impersonating needs a local Katana that skips validation, the slot Katana's options are unknown"
+        ));
+    }
+
+    if !node_config.disable_validate {
+        return Err(ApiError::conflict(
+            "impersonating needs Katana to skip validation, enable disable_validate through /api/katana/config"
+        ));
+    }
+
+    let target = contracts::resolve(state, &payload.contract).await?;
+    let function = target.abi.function(&payload.function)
+        .ok_or_else(|| ApiError::not_found(format!("no function `{}` in the ABI", payload.function)))?;
+
+    let calldata = target.abi.encode_inputs(function, &payload.args)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    // `__execute__` of a Cairo 1 account takes an array of calls
+    let mut execute_calldata = vec![FieldElement::ONE, target.address, get_selector_from_name(&function.name)?];
+    execute_calldata.push(calldata.len().into());
+    execute_calldata.extend(calldata);
+
+    let provider = chain::provider(state);
+    let nonce = provider.get_nonce(BlockId::Tag(BlockTag::Pending), payload.address).await
+        .map_err(|_| ApiError::not_found(format!("no account at {:#x}", payload.address)))?;
+
+    let mut transaction = BroadcastedInvokeTransactionV1 {
+        sender_address: payload.address,
+        calldata: execute_calldata,
+        max_fee: FieldElement::ZERO,
+        signature: DUMMY_SIGNATURE.to_vec(),
+        nonce,
+        is_query: false,
+    };
+
+    transaction.max_fee = match payload.max_fee {
+        Some(max_fee) => max_fee,
+        None => {
+            let query = BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(
+                BroadcastedInvokeTransactionV1 { is_query: true, ..transaction.clone() }
+            ));
+            let estimate = provider.estimate_fee_single(
+                query,
+                [SimulationFlagForEstimateFee::SkipValidate],
+                BlockId::Tag(BlockTag::Pending),
+            ).await.map_err(|error| ApiError::bad_request(format!("fee estimation failed: {}", error)))?;
+
+            // room for the state to change before it's mined
+            estimate.overall_fee * FieldElement::TWO
+        }
+    };
+
+    let result = provider.add_invoke_transaction(BroadcastedInvokeTransaction::V1(transaction)).await
+        .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+    let receipt = chain::wait_for_receipt(&provider, result.transaction_hash).await
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+    // the transaction went through, not remembering the account doesn't make it fail
+    if let Err(error) = remember(state, payload.address) {
+        eprintln!("Failed to remember the impersonated account {:#x}: {}", payload.address, error.message());
+    }
+
+    Ok(Invoked {
+        transaction_hash: result.transaction_hash,
+        receipt,
+    })
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Impersonate>,
+) -> ApiResult<Json<Invoked>> {
+    Ok(Json(impersonate(&state, &payload).await?))
+}
@@ -0,0 +1,67 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::accounts::{Account, Call, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{FieldElement, MaybePendingTransactionReceipt};
+use starknet::core::utils::get_selector_from_name;
+use crate::chain;
+use crate::contracts;
+use crate::error::{ApiError, ApiResult};
+use crate::server_state::ServerState;
+
+#[derive(Deserialize)]
+pub struct Invoke {
+    /// name (`actions`, `dojo_examples::actions::actions`) or address
+    pub contract: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Value,
+    /// the dev account sending it, the first one in the genesis by default
+    pub account: Option<FieldElement>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Invoked {
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: FieldElement,
+    pub receipt: MaybePendingTransactionReceipt,
+}
+
+/**
+ * sends a transaction from a dev account and waits for its receipt
+ */
+pub async fn invoke(state: &ServerState, payload: &Invoke) -> ApiResult<Invoked> {
+    let target = contracts::resolve(state, &payload.contract).await?;
+    let function = target.abi.function(&payload.function)
+        .ok_or_else(|| ApiError::not_found(format!("no function `{}` in the ABI", payload.function)))?;
+
+    let calldata = target.abi.encode_inputs(function, &payload.args)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    let account = chain::dev_signer(state, payload.account).await?;
+    let call = Call {
+        to: target.address,
+        selector: get_selector_from_name(&function.name)?,
+        calldata,
+    };
+
+    let result = account.execute(vec![call]).send().await
+        .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+    let receipt = chain::wait_for_receipt(account.provider(), result.transaction_hash).await
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    Ok(Invoked {
+        transaction_hash: result.transaction_hash,
+        receipt,
+    })
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Invoke>,
+) -> ApiResult<Json<Invoked>> {
+    Ok(Json(invoke(&state, &payload).await?))
+}
@@ -0,0 +1,50 @@
+use axum::{Extension, Json};
+use serde::Serialize;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::contract::AbiFunction;
+use starknet::core::types::FieldElement;
+use crate::error::{ApiError, ApiResult};
+use crate::manifest::Manifest;
+use crate::server_state::ServerState;
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Contract {
+    name: String,
+    #[serde_as(as = "Option<UfeHex>")]
+    address: Option<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    class_hash: FieldElement,
+    functions: Vec<AbiFunction>,
+    /// why there are no functions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    abi_error: Option<String>,
+}
+
+/**
+ * the world and its contracts from the manifest, with the functions the console can run
+ */
+pub async fn handler(Extension(state): Extension<ServerState>) -> ApiResult<Json<Vec<Contract>>> {
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+
+    let contracts = manifest.all_contracts()
+        .map(|contract| {
+            let (functions, abi_error) = match manifest.abi(contract) {
+                Ok(abi) => (abi.functions().to_vec(), None),
+                Err(error) => (vec![], Some(format!("{:#}", error)))
+            };
+
+            Contract {
+                name: contract.name.clone(),
+                address: contract.address,
+                class_hash: contract.class_hash,
+                functions,
+                abi_error,
+            }
+        })
+        .collect();
+
+    Ok(Json(contracts))
+}
@@ -0,0 +1,6 @@
+pub mod call;
+pub mod declare;
+pub mod hash;
+pub mod impersonate;
+pub mod invoke;
+pub mod list;
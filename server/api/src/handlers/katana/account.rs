@@ -1,54 +1,44 @@
-// use std::string::ToString;
-// use axum::Extension;
-// use axum::response::{IntoResponse, Json};
-// use katana_primitives::genesis::allocation::{GenesisAccount, DevAllocationsGenerator, DevGenesisAccount};
-// use serde::{Deserialize, Serialize};
-// use serde_json::json;
-// use crate::server_state::ServerState;
-//
-// #[derive(Debug, Clone, Deserialize, Serialize)]
-// pub struct SerializedAccount {
-//     pub public_key: String,
-//     pub private_key: String,
-//     pub address: String,
-//     pub class_hash: String,
-// }
-//
-// impl From<DevGenesisAccount> for SerializedAccount {
-//     fn from(value: DevGenesisAccount) -> Self {
-//         SerializedAccount {
-//             public_key: value.public_key.to_string(),
-//             private_key: value.private_key.to_string(),
-//             address: value.inner.address.to_string(),
-//             class_hash: value.class_hash.to_string(),
-//         }
-//     }
-// }
-//
-// fn parse_seed(seed: &str) -> [u8; 32] {
-//     let seed = seed.as_bytes();
-//
-//     if seed.len() >= 32 {
-//         unsafe { *(seed[..32].as_ptr() as *const [u8; 32]) }
-//     } else {
-//         let mut actual_seed = [0u8; 32];
-//         seed.iter().enumerate().for_each(|(i, b)| actual_seed[i] = *b);
-//         actual_seed
-//     }
-// }
-//
-// fn generate_accounts(seed: &str, total_accounts: u8) -> Vec<DevGenesisAccount> {
-//     DevAllocationsGenerator::new(total_accounts as u16)
-//         .with_seed(parse_seed(seed))
-//         .generate()
-// }
-//
-// pub fn get_serialized_accounts(seed: &str, total_accounts: u8) -> Vec<SerializedAccount> {
-//     let accounts = generate_accounts(seed, total_accounts);
-//
-//     accounts.iter().map(|account| account.clone().into()).collect()
-// }
-//
-// pub async fn handler(Extension(state): Extension<ServerState>) -> impl IntoResponse {
-//     Json(json!(generate_accounts(&state.starknet.seed, state.starknet.total_accounts)))
-// }
+use axum::{Extension, Json};
+use serde::Serialize;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::Genesis;
+use crate::genesis_builder::ACCOUNT_CLASS_HASH;
+use crate::server_state::ServerState;
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct SerializedAccount {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub public_key: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub private_key: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub class_hash: FieldElement,
+}
+
+/**
+ * the dev accounts of the genesis Katana runs, the generated ones when the genesis is generated
+ */
+pub fn dev_accounts(state: &ServerState) -> ApiResult<Vec<SerializedAccount>> {
+    let genesis = Genesis::read(&state.genesis_path())
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let default_class_hash = FieldElement::from_hex_be(ACCOUNT_CLASS_HASH)?;
+
+    Ok(genesis.accounts.into_iter()
+        .filter_map(|(address, account)| account.private_key.map(|private_key| SerializedAccount {
+            address,
+            public_key: account.public_key,
+            private_key,
+            class_hash: account.class.unwrap_or(default_class_hash),
+        }))
+        .collect())
+}
+
+pub async fn handler(Extension(state): Extension<ServerState>) -> ApiResult<Json<Vec<SerializedAccount>>> {
+    Ok(Json(dev_accounts(&state)?))
+}
@@ -1,15 +1,17 @@
 use axum::{Extension, Json, response::Json as AxumJson};
-use axum::response::IntoResponse;
 use jsonrpsee_core::client::ClientT;
 use jsonrpsee_core::params::ArrayParams;
 use jsonrpsee_http_client::{HttpClient};
 use serde::Deserialize;
+use crate::error::ApiResult;
+use crate::metrics::Metrics;
 use crate::server_state::ServerState;
 
 #[derive(Deserialize)]
 enum Type {
     MineBlock(u64),
-    IncreaseTime(u64)
+    IncreaseTime(u64),
+    SetTimestamp(u64)
 }
 
 #[derive(Deserialize)]
@@ -17,38 +19,62 @@ pub struct Manipulation {
     action: Type
 }
 
-async fn mine_one(client: &HttpClient) {
+async fn mine_one(client: &HttpClient, metrics: &Metrics) -> anyhow::Result<()> {
     client.request::<(), ArrayParams>(
         "katana_generateBlock",
         ArrayParams::default()
-    ).await.expect("able to mine");
+    ).await?;
+    metrics.blocks_mined.inc();
+    Ok(())
 }
 
-async fn mine_block(blocks: u64, client: &HttpClient) -> AxumJson<String> {
+/**
+ * mines empty blocks, one after the other
+ */
+pub async fn mine_block(blocks: u64, client: &HttpClient, metrics: &Metrics) -> anyhow::Result<String> {
     for _ in 0..blocks  {
-        mine_one(client).await
+        mine_one(client, metrics).await?;
     }
-    AxumJson(format!("Mined {} blocks", blocks))
+    Ok(format!("Mined {} blocks", blocks))
 }
 
-async fn increase_block_time(seconds: u64, client: &HttpClient) -> AxumJson<String> {
+/**
+ * moves the clock forward and mines a block with the new time
+ */
+pub async fn increase_block_time(seconds: u64, client: &HttpClient, metrics: &Metrics) -> anyhow::Result<String> {
     let mut params = ArrayParams::new();
-    params.insert(seconds).expect("able to add seconds");
+    params.insert(seconds)?;
     client.request::<(), ArrayParams>(
         "katana_increaseNextBlockTimestamp",
         params
-    ).await.expect("able to increase time");
-    mine_one(client).await;
-    AxumJson(format!("Increased block time by {} seconds", seconds))
+    ).await?;
+    mine_one(client, metrics).await?;
+    Ok(format!("Increased block time by {} seconds", seconds))
+}
+
+/**
+ * mines a block with the given unix timestamp, it can't be before the last block's
+ */
+pub async fn set_block_timestamp(timestamp: u64, client: &HttpClient, metrics: &Metrics) -> anyhow::Result<String> {
+    let mut params = ArrayParams::new();
+    params.insert(timestamp)?;
+    client.request::<(), ArrayParams>(
+        "katana_setNextBlockTimestamp",
+        params
+    ).await?;
+    mine_one(client, metrics).await?;
+    Ok(format!("Set block timestamp to {}", timestamp))
 }
 
 pub async fn handler(
     Extension(state): Extension<ServerState>,
     Json(payload): Json<Manipulation>
-) -> impl IntoResponse {
+) -> ApiResult<AxumJson<String>> {
     let json_rpc_client = &state.json_rpc_client;
-    match payload.action {
-        Type::MineBlock(blocks) => mine_block(blocks, json_rpc_client).await,
-        Type::IncreaseTime(seconds) => increase_block_time(seconds, json_rpc_client).await
-    }
-}
\ No newline at end of file
+    let message = match payload.action {
+        Type::MineBlock(blocks) => mine_block(blocks, json_rpc_client, &state.metrics).await?,
+        Type::IncreaseTime(seconds) => increase_block_time(seconds, json_rpc_client, &state.metrics).await?,
+        Type::SetTimestamp(timestamp) => set_block_timestamp(timestamp, json_rpc_client, &state.metrics).await?
+    };
+    Ok(AxumJson(message))
+}
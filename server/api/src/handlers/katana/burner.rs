@@ -0,0 +1,171 @@
+use std::fs;
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::accounts::{Account, Call, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::utils::{get_selector_from_name, get_udc_deployed_address, UdcUniqueness};
+use starknet::signers::SigningKey;
+use crate::chain;
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::{Genesis, UDC_ADDRESS};
+use crate::genesis_builder::ACCOUNT_CLASS_HASH;
+use crate::handlers::katana::funds::{self, Amount, FundAddress, Funded};
+use crate::server_state::ServerState;
+
+/// ETH a burner gets when the request doesn't say, in whole tokens.
+const DEFAULT_ETH: u64 = 10;
+
+/// Burners deployed in one transaction at most.
+const MAX_BURNERS: u16 = 100;
+
+#[serde_as]
+#[derive(Deserialize, Default)]
+pub struct CreateBurners {
+    /// how many, one by default
+    pub count: Option<u16>,
+    /// an account class whose constructor takes the public key, Katana's OpenZeppelin account by default
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class_hash: Option<FieldElement>,
+    /// the dev account deploying them, the first one in the genesis by default
+    #[serde_as(as = "Option<UfeHex>")]
+    pub funder: Option<FieldElement>,
+    /// in whole ETH
+    pub eth: Option<Amount>,
+    #[serde(default)]
+    pub tokens: Vec<TokenAmount>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenAmount {
+    pub token_address: String,
+    pub amount: Amount,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BurnerAccount {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub public_key: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub private_key: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub class_hash: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: FieldElement,
+}
+
+#[derive(Serialize)]
+pub struct Burner {
+    #[serde(flatten)]
+    pub account: BurnerAccount,
+    pub balances: Vec<Funded>,
+}
+
+/**
+ * the burners created so far on this world
+ */
+pub fn burners(state: &ServerState) -> ApiResult<Vec<BurnerAccount>> {
+    match fs::read_to_string(state.burners_path()) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+fn save_burners(state: &ServerState, created: &[BurnerAccount]) -> ApiResult<()> {
+    let _lock = state.accounts_file_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut all = burners(state)?;
+    all.extend_from_slice(created);
+    fs::write(state.burners_path(), serde_json::to_string_pretty(&all)?)?;
+    Ok(())
+}
+
+/**
+ * deploys new accounts with fresh keys through the Universal Deployer from a dev account, then funds them
+ */
+pub async fn create_burners(state: &ServerState, payload: &CreateBurners) -> ApiResult<Vec<Burner>> {
+    let count = payload.count.unwrap_or(1);
+    if count == 0 || count > MAX_BURNERS {
+        return Err(ApiError::bad_request(format!("count must be between 1 and {}", MAX_BURNERS)));
+    }
+
+    let class_hash = payload.class_hash.map_or_else(|| FieldElement::from_hex_be(ACCOUNT_CLASS_HASH), Ok)?;
+    let udc_address = Genesis::read(&state.genesis_path()).ok()
+        .and_then(|genesis| genesis.universal_deployer_address())
+        .map_or_else(|| FieldElement::from_hex_be(UDC_ADDRESS), Ok)?;
+    let deploy_selector = get_selector_from_name("deployContract")?;
+
+    let mut keys = vec![];
+    let mut calls = vec![];
+    for _ in 0..count {
+        let signing_key = SigningKey::from_random();
+        let public_key = signing_key.verifying_key().scalar();
+
+        // the public key is the salt too, the same key always ends up at the same address
+        calls.push(Call {
+            to: udc_address,
+            selector: deploy_selector,
+            calldata: vec![class_hash, public_key, FieldElement::ZERO, FieldElement::ONE, public_key],
+        });
+        keys.push((signing_key.secret_scalar(), public_key));
+    }
+
+    let signer = chain::dev_signer(state, payload.funder).await?;
+    let result = signer.execute(calls).send().await
+        .map_err(|error| ApiError::bad_request(format!("transaction failed: {}", error)))?;
+    chain::wait_for_receipt(signer.provider(), result.transaction_hash).await
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    let created: Vec<BurnerAccount> = keys.into_iter()
+        .map(|(private_key, public_key)| BurnerAccount {
+            address: get_udc_deployed_address(public_key, class_hash, &UdcUniqueness::NotUnique, &[public_key]),
+            public_key,
+            private_key,
+            class_hash,
+            transaction_hash: result.transaction_hash,
+        })
+        .collect();
+    // saved before funding, the accounts are deployed even when funding them fails
+    save_burners(state, &created)?;
+
+    let mut burners = vec![];
+    for account in created {
+        let address = format!("{:#x}", account.address);
+
+        let mut funding = vec![FundAddress {
+            address: address.clone(),
+            amount: payload.eth.clone().unwrap_or_else(|| Amount::from(DEFAULT_ETH)),
+            token_address: None,
+        }];
+        funding.extend(payload.tokens.iter().map(|token| FundAddress {
+            address: address.clone(),
+            amount: token.amount.clone(),
+            token_address: Some(token.token_address.clone()),
+        }));
+
+        let mut balances = vec![];
+        for fund in &funding {
+            balances.push(funds::fund(state, fund).await?);
+        }
+
+        burners.push(Burner { account, balances });
+    }
+
+    Ok(burners)
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    payload: Option<Json<CreateBurners>>,
+) -> ApiResult<Json<Vec<Burner>>> {
+    let Json(payload) = payload.unwrap_or_default();
+    Ok(Json(create_burners(&state, &payload).await?))
+}
+
+pub async fn list_handler(Extension(state): Extension<ServerState>) -> ApiResult<Json<Vec<BurnerAccount>>> {
+    Ok(Json(burners(&state)?))
+}
@@ -0,0 +1,63 @@
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tokio::sync::OwnedMutexGuard;
+use crate::node_config::KatanaUpdate;
+use crate::progress::Progress;
+use crate::server_state::ServerState;
+use crate::supervisor::{Restarted, Service};
+
+async fn restart(state: ServerState, progress: Progress, update: KatanaUpdate, args: Vec<String>, _lock: OwnedMutexGuard<()>) {
+    progress.step("Restarting Katana on the same database").await;
+
+    // the options are only stored once Katana runs with them
+    match state.supervisor.restart_or_rollback(Service::Katana, args).await {
+        Ok(Restarted::Started) => {
+            if let Err(errors) = state.config.update_katana(&update) {
+                progress.error(&format!("Katana runs with the new options, but they couldn't be stored: {}", errors.join(", "))).await;
+                return;
+            }
+        }
+        Ok(Restarted::RolledBack(error)) => {
+            progress.error(&format!("Katana didn't start with the new options, it runs with the previous ones: {}", error)).await;
+            return;
+        }
+        Err(error) => {
+            progress.error(&format!("Failed to restart Katana: {}", error)).await;
+            return;
+        }
+    }
+
+    progress.step("Katana is ready").await;
+
+    if state.config.torii_follows_katana() {
+        progress.step("Restarting Torii").await;
+
+        if let Err(error) = state.supervisor.restart(Service::Torii, state.config.torii_args()).await {
+            progress.error(&format!("Failed to restart Torii: {}", error)).await;
+            return;
+        }
+
+        progress.step("Torii is ready").await;
+    }
+
+    progress.done("Katana is reconfigured").await;
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(update): Json<KatanaUpdate>,
+) -> Response {
+    // one update at a time, from validating it until Katana runs with it
+    let lock = state.katana_update_lock.clone().lock_owned().await;
+
+    let args = match state.config.updated_katana_args(&update) {
+        Ok(args) => args,
+        Err(errors) => return (StatusCode::BAD_REQUEST, Json(errors)).into_response(),
+    };
+
+    let (progress, stream) = Progress::channel();
+    tokio::spawn(restart(state, progress, update, args, lock));
+
+    stream.into_response()
+}
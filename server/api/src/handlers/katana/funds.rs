@@ -1,51 +1,232 @@
+use std::str::FromStr;
 use axum::{Extension, Json};
-use axum::response::IntoResponse;
-use jsonrpsee_http_client::HttpClient;
-use serde::Deserialize;
+use num_bigint::BigUint;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement};
+use starknet::core::utils::get_storage_var_address;
+use starknet::providers::Provider;
+use crate::chain;
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::{Genesis, BALANCES_VARIABLE, ETH_ADDRESS, TOTAL_SUPPLY_VARIABLE};
 use crate::server_state::ServerState;
 
+/// Tokens are assumed to have 18 decimals.
+const DECIMALS: usize = 18;
+
 #[derive(Deserialize)]
 pub struct FundAddress {
-    address: String,
-    amount: f32,
-    token_address: Option<String>,
+    pub address: String,
+    pub amount: Amount,
+    pub token_address: Option<String>,
+}
+
+/// An amount in whole tokens, `10`, `0.5` or `"0.000000000000000001"`. Kept as written, so it
+/// converts to wei exactly.
+#[derive(Debug, Clone)]
+pub struct Amount(String);
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct Funded {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub token_address: FieldElement,
+    /// the new balance in wei, as a decimal string
+    pub balance: String,
+}
+
+impl From<u64> for Amount {
+    fn from(amount: u64) -> Self {
+        Amount(amount.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            // printed in its shortest form, so 0.1 stays 0.1
+            Float(f64),
+            Text(String),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Integer(amount) => Amount(amount.to_string()),
+            Raw::Float(amount) => Amount(amount.to_string()),
+            Raw::Text(amount) => Amount(amount.trim().to_string()),
+        })
+    }
+}
+
+impl Amount {
+    /**
+     * the amount in wei
+     */
+    pub fn to_wei(&self) -> ApiResult<BigUint> {
+        let invalid = || ApiError::bad_request(format!("`{}` is not a positive decimal amount", self.0));
+
+        let (whole, fraction) = self.0.split_once('.').unwrap_or((&self.0, ""));
+        let is_digits = |digits: &str| digits.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > DECIMALS {
+            return Err(ApiError::bad_request(format!("`{}` has more than {} decimals", self.0, DECIMALS)));
+        }
+
+        let wei = format!("{}{:0<width$}", whole, fraction, width = DECIMALS);
+        let wei = BigUint::from_str(&wei).map_err(|_| invalid())?;
+        if wei.bits() > 256 {
+            return Err(ApiError::bad_request(format!("`{}` doesn't fit in a u256", self.0)));
+        }
+        Ok(wei)
+    }
+}
+
+fn parse_address(address: &str) -> ApiResult<FieldElement> {
+    FieldElement::from_hex_be(address)
+        .map_err(|_| ApiError::bad_request(format!("`{}` is not a hex address", address)))
+}
+
+/**
+ * adds to a u256 in the token's storage, the high half is in the next slot. Returns the new value
+ */
+async fn add_u256(state: &ServerState, token_address: FieldElement, low_key: FieldElement, amount: &BigUint) -> ApiResult<BigUint> {
+    let high_key = low_key + FieldElement::ONE;
+
+    let provider = chain::provider(state);
+    let read = |key| provider.get_storage_at(token_address, key, BlockId::Tag(BlockTag::Pending));
+    let low = read(low_key).await.map_err(|error| ApiError::not_found(format!("failed to read the balance: {}", error)))?;
+    let high = read(high_key).await.map_err(|error| ApiError::not_found(format!("failed to read the balance: {}", error)))?;
+
+    let to_biguint = |felt: FieldElement| BigUint::from_bytes_be(&felt.to_bytes_be());
+    let value = (to_biguint(high) << 128u32) + to_biguint(low) + amount;
+    if value.bits() > 256 {
+        return Err(ApiError::bad_request("the new balance doesn't fit in a u256"));
+    }
+
+    let mask = (BigUint::from(1u8) << 128u32) - 1u8;
+    let to_felt = |value: BigUint| FieldElement::from_byte_slice_be(&value.to_bytes_be());
+    chain::set_storage_at(state, token_address, low_key, to_felt(&value & &mask)?).await?;
+    chain::set_storage_at(state, token_address, high_key, to_felt(&value >> 128u32)?).await?;
+
+    Ok(value)
+}
+
+/**
+ * adds to the balance and the total supply by writing the token's storage, no transaction or minter needed
+ */
+async fn increase_balance(
+    state: &ServerState,
+    address: FieldElement,
+    token_address: FieldElement,
+    amount: &Amount,
+) -> ApiResult<Funded> {
+    let wei = amount.to_wei()?;
+
+    let balance_key = get_storage_var_address(BALANCES_VARIABLE, &[address])?;
+
+    // a read and a write each, two fundings at once would lose one of them otherwise
+    let _lock = state.funding_lock.lock().await;
+    let balance = add_u256(state, token_address, balance_key, &wei).await?;
+    let total_supply_key = get_storage_var_address(TOTAL_SUPPLY_VARIABLE, &[])?;
+    add_u256(state, token_address, total_supply_key, &wei).await?;
+
+    Ok(Funded {
+        address,
+        token_address,
+        balance: balance.to_string(),
+    })
 }
 
 async fn increase_token(
-    _client: &HttpClient,
-    _address: String,
-    _token_address:
-    String,
-    _amount: f32,
-) -> () {
-    todo!()
+    state: &ServerState,
+    address: FieldElement,
+    token_address: FieldElement,
+    amount: &Amount,
+) -> ApiResult<Funded> {
+    increase_balance(state, address, token_address, amount).await
 }
 
 async fn increase_eth(
-    _client: &HttpClient,
-    _address: String,
-    _amount: f32
-) -> () {
-    todo!()
+    state: &ServerState,
+    address: FieldElement,
+    amount: &Amount,
+) -> ApiResult<Funded> {
+    let eth_address = Genesis::read(&state.genesis_path()).ok()
+        .and_then(|genesis| genesis.fee_token_address())
+        .map_or_else(|| FieldElement::from_hex_be(ETH_ADDRESS), Ok)?;
+
+    increase_balance(state, address, eth_address, amount).await
+}
+
+/**
+ * funds an address with ETH, or with the given token
+ */
+pub async fn fund(state: &ServerState, payload: &FundAddress) -> ApiResult<Funded> {
+    let address = parse_address(&payload.address)?;
+
+    let funded = match &payload.token_address {
+        None => increase_eth(
+            state,
+            address,
+            &payload.amount,
+        ).await?,
+        Some(token_address) => increase_token(
+            state,
+            address,
+            parse_address(token_address)?,
+            &payload.amount,
+        ).await?
+    };
+
+    // bucketed, the label takes any string the client sends
+    let token = if payload.token_address.is_none() { "eth" } else { "other" };
+    state.metrics.funding_operations.with_label_values(&[token]).inc();
+
+    Ok(funded)
 }
 
 pub async fn handler(
     Extension(state): Extension<ServerState>,
     Json(payload): Json<FundAddress>,
-) -> impl IntoResponse {
-    let json_rpc_client = &state.json_rpc_client;
+) -> ApiResult<Json<Funded>> {
+    Ok(Json(fund(&state, &payload).await?))
+}
 
-    match payload.token_address {
-        None => increase_eth(
-            json_rpc_client,
-            payload.address,
-            payload.amount,
-        ).await,
-        Some(token_address) => increase_token(
-            json_rpc_client,
-            payload.address,
-            token_address,
-            payload.amount,
-        ).await
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    fn wei(amount: serde_json::Value) -> ApiResult<String> {
+        let amount: Amount = serde_json::from_value(amount).unwrap();
+        amount.to_wei().map(|wei| wei.to_string())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn converts_whole_tokens_to_wei() {
+        assert_eq!(wei(json!(10)).unwrap(), "10000000000000000000");
+        assert_eq!(wei(json!(0.5)).unwrap(), "500000000000000000");
+        assert_eq!(wei(json!(0.1)).unwrap(), "100000000000000000");
+        assert_eq!(wei(json!("0.000000000000000001")).unwrap(), "1");
+        assert_eq!(wei(json!(" 1.25 ")).unwrap(), "1250000000000000000");
+        assert_eq!(wei(json!(".5")).unwrap(), "500000000000000000");
+        assert_eq!(wei(json!("0")).unwrap(), "0");
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_positive_amount() {
+        for amount in [json!(-1), json!("-1"), json!("1e3"), json!("0x10"), json!("."), json!(""), json!("1.2.3")] {
+            assert!(wei(amount.clone()).is_err(), "{}", amount);
+        }
+
+        assert!(wei(json!("0.0000000000000000001")).unwrap_err().message().contains("more than 18 decimals"));
+        assert!(wei(json!("9".repeat(60))).unwrap_err().message().contains("doesn't fit in a u256"));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod account;
 pub mod block;
+pub mod burner;
+pub mod config;
 pub mod funds;
-pub mod state;
\ No newline at end of file
+pub mod overview;
+pub mod state;
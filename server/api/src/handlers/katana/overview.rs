@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use jsonrpsee_core::client::ClientT;
+use jsonrpsee_core::params::{ArrayParams, BatchRequestBuilder};
+use num_bigint::BigUint;
+use serde::Serialize;
+use serde_json::{json, Value};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::utils::get_selector_from_name;
+use crate::error::{ApiError, ApiResult};
+use crate::genesis::{Genesis, ETH_ADDRESS, STRK_ADDRESS};
+use crate::handlers::contracts::impersonate;
+use crate::handlers::katana::burner;
+use crate::server_state::ServerState;
+
+/// How Keiko knows about an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    Genesis,
+    Burner,
+    Impersonated,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct AccountOverview {
+    #[serde_as(as = "UfeHex")]
+    pub address: FieldElement,
+    pub origins: Vec<Origin>,
+    /// None when nothing is deployed at the address
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class_hash: Option<FieldElement>,
+    #[serde_as(as = "Option<UfeHex>")]
+    pub nonce: Option<FieldElement>,
+    pub balances: Vec<TokenBalance>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct TokenBalance {
+    /// `ETH`, `STRK`, or the address of a configured token
+    pub token: String,
+    #[serde_as(as = "UfeHex")]
+    pub token_address: FieldElement,
+    /// in wei, as a decimal string, None when the token can't be asked
+    pub balance: Option<String>,
+}
+
+fn params(values: Vec<Value>) -> ApiResult<ArrayParams> {
+    let mut params = ArrayParams::new();
+    for value in values {
+        params.insert(value)?;
+    }
+    Ok(params)
+}
+
+/// A u256 as returned by `balanceOf`, the low half first.
+fn u256(value: &Value) -> Option<String> {
+    let felts: Vec<FieldElement> = value.as_array()?.iter()
+        .map(|felt| felt.as_str().and_then(|felt| FieldElement::from_hex_be(felt).ok()))
+        .collect::<Option<_>>()?;
+    let to_biguint = |felt: &FieldElement| BigUint::from_bytes_be(&felt.to_bytes_be());
+
+    match felts.as_slice() {
+        [low] => Some(to_biguint(low).to_string()),
+        [low, high, ..] => Some(((to_biguint(high) << 128u32) + to_biguint(low)).to_string()),
+        [] => None,
+    }
+}
+
+/**
+ * every account Keiko knows about: the genesis accounts, the burners and the impersonated ones
+ */
+pub fn known_accounts(state: &ServerState) -> ApiResult<BTreeMap<FieldElement, Vec<Origin>>> {
+    let mut accounts: BTreeMap<FieldElement, Vec<Origin>> = BTreeMap::new();
+
+    if let Ok(genesis) = Genesis::read(&state.genesis_path()) {
+        for address in genesis.accounts.keys() {
+            accounts.entry(*address).or_default().push(Origin::Genesis);
+        }
+    }
+    for burner in burner::burners(state)? {
+        accounts.entry(burner.address).or_default().push(Origin::Burner);
+    }
+    for address in impersonate::impersonated(state)? {
+        accounts.entry(address).or_default().push(Origin::Impersonated);
+    }
+
+    for origins in accounts.values_mut() {
+        origins.sort();
+        origins.dedup();
+    }
+    Ok(accounts)
+}
+
+/**
+ * the live balances, nonce and class hash of every known account, asked in one batch
+ */
+pub async fn overview(state: &ServerState) -> ApiResult<Vec<AccountOverview>> {
+    let accounts = known_accounts(state)?;
+
+    let eth_address = Genesis::read(&state.genesis_path()).ok()
+        .and_then(|genesis| genesis.fee_token_address())
+        .map_or_else(|| FieldElement::from_hex_be(ETH_ADDRESS), Ok)?;
+    let mut tokens = vec![
+        ("ETH".to_string(), eth_address),
+        ("STRK".to_string(), FieldElement::from_hex_be(STRK_ADDRESS)?),
+    ];
+    for token in state.config.node_config().tokens {
+        let address = FieldElement::from_hex_be(&token)
+            .map_err(|_| ApiError::bad_request(format!("token `{}` is not a hex address", token)))?;
+        if !tokens.iter().any(|(_, known)| *known == address) {
+            tokens.push((token, address));
+        }
+    }
+
+    if accounts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let balance_of = format!("{:#x}", get_selector_from_name("balanceOf")?);
+    let mut batch = BatchRequestBuilder::new();
+    for address in accounts.keys() {
+        let address = format!("{:#x}", address);
+        batch.insert("starknet_getClassHashAt", params(vec![json!("pending"), json!(address)])?)?;
+        batch.insert("starknet_getNonce", params(vec![json!("pending"), json!(address)])?)?;
+        for (_, token_address) in &tokens {
+            let call = json!({
+                "contract_address": format!("{:#x}", token_address),
+                "entry_point_selector": balance_of,
+                "calldata": [address],
+            });
+            batch.insert("starknet_call", params(vec![call, json!("pending")])?)?;
+        }
+    }
+
+    let responses: Vec<Option<Value>> = state.json_rpc_client.batch_request::<Value>(batch).await
+        .map_err(|error| ApiError::new(StatusCode::BAD_GATEWAY, format!("Katana didn't answer: {}", error)))?
+        .into_iter()
+        .map(Result::ok)
+        .collect();
+    let felt = |value: &Option<Value>| value.as_ref()
+        .and_then(Value::as_str)
+        .and_then(|felt| FieldElement::from_hex_be(felt).ok());
+
+    let mut responses = responses.chunks(2 + tokens.len());
+    let mut overview = vec![];
+    for (address, origins) in accounts {
+        let Some(responses) = responses.next() else { break };
+
+        overview.push(AccountOverview {
+            address,
+            origins,
+            class_hash: felt(&responses[0]),
+            nonce: felt(&responses[1]),
+            balances: tokens.iter().zip(&responses[2..])
+                .map(|((token, token_address), response)| TokenBalance {
+                    token: token.clone(),
+                    token_address: *token_address,
+                    balance: response.as_ref().and_then(u256),
+                })
+                .collect(),
+        });
+    }
+
+    Ok(overview)
+}
+
+pub async fn handler(Extension(state): Extension<ServerState>) -> ApiResult<Json<Vec<AccountOverview>>> {
+    Ok(Json(overview(&state).await?))
+}
@@ -1,13 +1,14 @@
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 
 pub async fn save_state() -> impl IntoResponse {
-    todo!()
+    StatusCode::NOT_IMPLEMENTED
 }
 
 pub async fn load_state() -> impl IntoResponse {
-    todo!()
+    StatusCode::NOT_IMPLEMENTED
 }
 
 pub async fn reset_state() -> impl IntoResponse {
-    todo!()
-}
\ No newline at end of file
+    StatusCode::NOT_IMPLEMENTED
+}
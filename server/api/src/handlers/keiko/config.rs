@@ -1,13 +1,16 @@
 use axum::http::StatusCode;
 use axum::{Extension, response::Json };
 use axum::response::IntoResponse;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
+use crate::node_config::{redact_url, NodeConfig};
 use crate::server_state::ServerState;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct Config {
     rpc_url: String,
-    torii_url: String
+    torii_url: String,
+    #[serde(flatten)]
+    node: NodeConfig
 }
 
 pub async fn handler(Extension(server_state): Extension<ServerState>) -> impl IntoResponse {
@@ -15,13 +18,10 @@ pub async fn handler(Extension(server_state): Extension<ServerState>) -> impl In
         StatusCode::OK,
         Json(
             Config {
-                rpc_url: server_state.rpc_url.into(),
-                torii_url: server_state.torii_url.into()
+                rpc_url: redact_url(&server_state.rpc_url),
+                torii_url: redact_url(&server_state.torii_url),
+                node: server_state.config.node_config()
             }
         )
     )
 }
-
-
-
-
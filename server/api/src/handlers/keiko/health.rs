@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use axum::Extension;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json};
+use jsonrpsee_core::client::ClientT;
+use jsonrpsee_core::params::ArrayParams;
+use nix::sys::statvfs::statvfs;
+use serde::Serialize;
+use crate::indexing;
+use crate::server_state::ServerState;
+
+/// How long a single check may take before its target counts as unreachable.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How many blocks Torii may be behind Katana and still count as synced.
+const SYNC_TOLERANCE: u64 = 2;
+
+#[derive(Serialize)]
+struct Health {
+    healthy: bool,
+    ready: bool,
+    katana: KatanaHealth,
+    torii: ToriiHealth,
+    world: WorldHealth,
+    storage: Option<StorageHealth>,
+}
+
+#[derive(Serialize)]
+struct KatanaHealth {
+    reachable: bool,
+    head: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ToriiHealth {
+    reachable: bool,
+    /// None when Torii doesn't run locally and its progress is unknown
+    indexed_head: Option<u64>,
+    lag: Option<u64>,
+    synced: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct WorldHealth {
+    address: String,
+    deployed: bool,
+    class_hash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StorageHealth {
+    path: String,
+    used_bytes: u64,
+    available_bytes: u64,
+}
+
+async fn check_katana(state: &ServerState) -> KatanaHealth {
+    let head = tokio::time::timeout(CHECK_TIMEOUT, indexing::katana_head(&state.json_rpc_client)).await
+        .ok()
+        .and_then(|head| head.ok());
+
+    KatanaHealth {
+        reachable: head.is_some(),
+        head,
+    }
+}
+
+async fn check_torii(state: &ServerState, katana_head: Option<u64>) -> ToriiHealth {
+    // any response means Torii is up, its root doesn't serve anything
+    let reachable = reqwest::Client::new()
+        .get(state.torii_url.clone())
+        .timeout(CHECK_TIMEOUT)
+        .send().await
+        .is_ok();
+
+    let indexed_head = match &state.torii_db_path {
+        Some(torii_db_path) => indexing::torii_head(torii_db_path).await.ok(),
+        None => None
+    };
+
+    let lag = katana_head.zip(indexed_head).map(|(katana_head, indexed_head)| katana_head.saturating_sub(indexed_head));
+
+    ToriiHealth {
+        reachable,
+        indexed_head,
+        lag,
+        synced: lag.map(|lag| lag <= SYNC_TOLERANCE),
+    }
+}
+
+async fn check_world(state: &ServerState) -> WorldHealth {
+    let mut params = ArrayParams::new();
+    params.insert("latest").expect("able to add block id");
+    params.insert(&state.world_address).expect("able to add contract address");
+
+    let class_hash = tokio::time::timeout(
+        CHECK_TIMEOUT,
+        state.json_rpc_client.request::<String, ArrayParams>("starknet_getClassHashAt", params),
+    ).await
+        .ok()
+        .and_then(|class_hash| class_hash.ok());
+
+    WorldHealth {
+        address: state.world_address.clone(),
+        deployed: class_hash.is_some(),
+        class_hash,
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries.flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0
+        })
+        .sum()
+}
+
+async fn check_storage(state: &ServerState) -> Option<StorageHealth> {
+    let path = state.storage_dir.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let stats = statvfs(path.as_str()).ok()?;
+
+        Some(StorageHealth {
+            used_bytes: dir_size(Path::new(&path)),
+            available_bytes: stats.blocks_available() as u64 * stats.fragment_size() as u64,
+            path,
+        })
+    }).await.ok().flatten()
+}
+
+async fn check(state: &ServerState) -> Health {
+    let katana = check_katana(state).await;
+    let (torii, world, storage) = tokio::join!(
+        check_torii(state, katana.head),
+        check_world(state),
+        check_storage(state)
+    );
+
+    let ready = katana.reachable && torii.reachable && world.deployed;
+
+    Health {
+        healthy: ready && torii.synced != Some(false),
+        ready,
+        katana,
+        torii,
+        world,
+        storage,
+    }
+}
+
+fn status(ok: bool) -> StatusCode {
+    if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE }
+}
+
+/**
+ * the full report, 503 when anything is off
+ */
+pub async fn handler(Extension(state): Extension<ServerState>) -> impl IntoResponse {
+    let health = check(&state).await;
+    (status(health.healthy), Json(health))
+}
+
+/**
+ * Keiko itself answers. Doesn't look at Katana or Torii, so slow children never get Keiko restarted
+ */
+pub async fn live_handler() -> impl IntoResponse {
+    (StatusCode::OK, Json("live"))
+}
+
+/**
+ * Katana and Torii are reachable and the world is deployed, Torii may still be catching up
+ */
+pub async fn ready_handler(Extension(state): Extension<ServerState>) -> impl IntoResponse {
+    let health = check(&state).await;
+    (status(health.ready), Json(health))
+}
@@ -0,0 +1,32 @@
+use axum::Extension;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use crate::indexing;
+use crate::server_state::ServerState;
+
+async fn katana_metrics(state: &ServerState) -> Option<String> {
+    let url = state.katana_metrics_url.as_ref()?;
+
+    match reqwest::get(url.clone()).await {
+        Ok(response) => response.text().await.ok(),
+        Err(_) => None
+    }
+}
+
+pub async fn handler(Extension(state): Extension<ServerState>) -> impl IntoResponse {
+    if let Ok(Some(lag)) = indexing::torii_lag(&state).await {
+        state.metrics.torii_indexing_lag.set(lag as i64);
+    }
+
+    let mut body = state.metrics.encode();
+
+    if let Some(katana_metrics) = katana_metrics(&state).await {
+        body.push_str(&katana_metrics);
+    }
+
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body
+    )
+}
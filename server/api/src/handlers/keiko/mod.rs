@@ -1 +1,3 @@
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod health;
+pub mod metrics;
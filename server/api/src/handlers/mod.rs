@@ -1,2 +1,8 @@
+pub mod auth;
+pub mod contracts;
 pub mod katana;
-pub mod keiko;
\ No newline at end of file
+pub mod keiko;
+pub mod scenarios;
+pub mod storage;
+pub mod torii;
+pub mod world;
@@ -0,0 +1,17 @@
+use axum::{Extension, Json};
+use crate::error::{ApiError, ApiResult};
+use crate::scenario::{Report, Scenario};
+use crate::server_state::ServerState;
+
+/**
+ * runs a scenario given as the request body, in JSON or TOML, and reports each step
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    body: String,
+) -> ApiResult<Json<Report>> {
+    let scenario = Scenario::parse(&body)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    Ok(Json(scenario.run(&state).await))
+}
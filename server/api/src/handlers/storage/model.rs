@@ -0,0 +1,131 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement};
+use starknet::providers::Provider;
+use crate::chain;
+use crate::error::{ApiError, ApiResult};
+use crate::manifest::Manifest;
+use crate::model_storage::{EntityStorage, LayoutMember, ModelLayout};
+use crate::server_state::ServerState;
+
+#[derive(Deserialize)]
+pub struct Entity {
+    /// full or short name, `dojo_examples::models::position` or `position`
+    pub model: String,
+    /// an array, an object by name, or the value of the only key
+    pub keys: Value,
+}
+
+#[derive(Deserialize)]
+pub struct SetEntity {
+    #[serde(flatten)]
+    entity: Entity,
+    /// the members to change, by name
+    values: Map<String, Value>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct EntityValues {
+    model: String,
+    #[serde_as(as = "UfeHex")]
+    selector: FieldElement,
+    #[serde(flatten)]
+    storage: EntityStorage,
+    #[serde_as(as = "Vec<UfeHex>")]
+    raw: Vec<FieldElement>,
+    values: Value,
+    layout: Vec<LayoutMember>,
+}
+
+/**
+ * the model's full name and how its entities are stored, from the manifest
+ */
+pub fn layout(state: &ServerState, model: &str) -> ApiResult<(String, ModelLayout)> {
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let model = manifest.find_model(model)
+        .ok_or_else(|| ApiError::not_found(format!("no model `{}` in the manifest", model)))?;
+
+    let layout = ModelLayout::new(model, manifest.model_abi(model)?)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    Ok((model.name.clone(), layout))
+}
+
+pub fn world_address(state: &ServerState) -> ApiResult<FieldElement> {
+    Ok(FieldElement::from_hex_be(&state.world_address)?)
+}
+
+/**
+ * the entity's raw slots from the world's storage
+ */
+pub async fn read(state: &ServerState, world_address: FieldElement, storage: &EntityStorage) -> ApiResult<Vec<FieldElement>> {
+    let provider = chain::provider(state);
+    let mut raw = vec![];
+
+    for slot in &storage.slots {
+        raw.push(provider.get_storage_at(world_address, slot, BlockId::Tag(BlockTag::Pending)).await
+            .map_err(|error| ApiError::not_found(format!("failed to read the world's storage: {}", error)))?);
+    }
+
+    Ok(raw)
+}
+
+/**
+ * reads an entity, after writing the given members when there are any
+ */
+pub async fn entity_values(state: &ServerState, entity: &Entity, write: Option<&Map<String, Value>>) -> ApiResult<EntityValues> {
+    let (model, layout) = layout(state, &entity.model)?;
+    let world_address = world_address(state)?;
+
+    let storage = layout.entity(&entity.keys)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+    let mut raw = read(state, world_address, &storage).await?;
+
+    if let Some(values) = write {
+        let previous = raw.clone();
+        layout.encode(&mut raw, values)
+            .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+        for ((slot, value), previous) in storage.slots.iter().zip(&raw).zip(&previous) {
+            if value != previous {
+                chain::set_storage_at(state, world_address, *slot, *value).await?;
+            }
+        }
+
+        raw = read(state, world_address, &storage).await?;
+    }
+
+    Ok(EntityValues {
+        model,
+        selector: layout.selector,
+        values: layout.decode(&raw)?,
+        storage,
+        raw,
+        layout: layout.members,
+    })
+}
+
+/**
+ * where an entity of a model is stored in the world, and what is stored there
+ */
+pub async fn read_handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Entity>,
+) -> ApiResult<Json<EntityValues>> {
+    Ok(Json(entity_values(&state, &payload, None).await?))
+}
+
+/**
+ * overwrites members of an entity straight in the world's storage, without running a system
+ */
+pub async fn write_handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<SetEntity>,
+) -> ApiResult<Json<EntityValues>> {
+    Ok(Json(entity_values(&state, &payload.entity, Some(&payload.values)).await?))
+}
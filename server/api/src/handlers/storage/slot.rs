@@ -0,0 +1,89 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, FieldElement};
+use starknet::core::utils::get_storage_var_address;
+use starknet::providers::Provider;
+use crate::chain;
+use crate::contracts;
+use crate::error::{ApiError, ApiResult};
+use crate::server_state::ServerState;
+
+#[derive(Deserialize)]
+pub struct Slot {
+    /// name (`actions`, `dojo_examples::actions::actions`) or address
+    contract: String,
+    /// the raw storage key
+    key: Option<FieldElement>,
+    /// or the name of a storage variable, with the keys of a mapping
+    variable: Option<String>,
+    #[serde(default)]
+    keys: Vec<FieldElement>,
+}
+
+#[derive(Deserialize)]
+pub struct SetSlot {
+    #[serde(flatten)]
+    slot: Slot,
+    value: FieldElement,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct SlotValue {
+    #[serde_as(as = "UfeHex")]
+    contract_address: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    key: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    value: FieldElement,
+}
+
+impl Slot {
+    fn key(&self) -> ApiResult<FieldElement> {
+        match (&self.key, &self.variable) {
+            (Some(key), None) => Ok(*key),
+            (None, Some(variable)) => get_storage_var_address(variable, &self.keys)
+                .map_err(|error| ApiError::bad_request(format!("invalid variable name: {}", error))),
+            _ => Err(ApiError::bad_request("give either a key or a variable"))
+        }
+    }
+}
+
+async fn read(state: &ServerState, contract_address: FieldElement, key: FieldElement) -> ApiResult<SlotValue> {
+    let value = chain::provider(state).get_storage_at(contract_address, key, BlockId::Tag(BlockTag::Pending)).await
+        .map_err(|error| ApiError::not_found(format!("failed to read storage: {}", error)))?;
+
+    Ok(SlotValue {
+        contract_address,
+        key,
+        value,
+    })
+}
+
+/**
+ * reads a storage slot of any contract
+ */
+pub async fn read_handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Slot>,
+) -> ApiResult<Json<SlotValue>> {
+    let contract_address = contracts::resolve_address(&state, &payload.contract)?;
+    Ok(Json(read(&state, contract_address, payload.key()?).await?))
+}
+
+/**
+ * overwrites a storage slot of any contract, returns what Katana reads back
+ */
+pub async fn write_handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<SetSlot>,
+) -> ApiResult<Json<SlotValue>> {
+    let contract_address = contracts::resolve_address(&state, &payload.slot.contract)?;
+    let key = payload.slot.key()?;
+
+    chain::set_storage_at(&state, contract_address, key, payload.value).await?;
+
+    Ok(Json(read(&state, contract_address, key).await?))
+}
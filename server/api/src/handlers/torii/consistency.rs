@@ -0,0 +1,15 @@
+use axum::{Extension, Json};
+use crate::consistency::{self, ConsistencyCheck, ConsistencyReport};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+
+/**
+ * samples entities from Torii and compares them with the world's storage, an empty body checks every model
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    payload: Option<Json<ConsistencyCheck>>,
+) -> ApiResult<Json<ConsistencyReport>> {
+    let Json(payload) = payload.unwrap_or_default();
+    Ok(Json(consistency::check(&state, &payload).await?))
+}
@@ -0,0 +1,14 @@
+use axum::{Extension, Json};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+use crate::torii::{self, EntityPage, EntityQuery};
+
+/**
+ * a page of a model's entities from Torii, filtered by keys, as table rows
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<EntityQuery>,
+) -> ApiResult<Json<EntityPage>> {
+    Ok(Json(torii::entities(&state, &payload).await?))
+}
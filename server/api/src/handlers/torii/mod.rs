@@ -0,0 +1,3 @@
+pub mod consistency;
+pub mod entities;
+pub mod reindex;
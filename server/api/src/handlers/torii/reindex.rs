@@ -0,0 +1,114 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use crate::indexing;
+use crate::progress::Progress;
+use crate::server_state::ServerState;
+use crate::supervisor::Service;
+
+#[derive(Deserialize)]
+pub struct Reindex {
+    /// keep the old database next to the new one, instead of deleting it
+    #[serde(default = "default_archive")]
+    archive: bool,
+}
+
+fn default_archive() -> bool {
+    true
+}
+
+/**
+ * moves the database and its sqlite journal files out of the way
+ */
+fn remove_database(torii_db_path: &str, archive: bool) -> io::Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    for suffix in ["", "-wal", "-shm"] {
+        let path = format!("{}{}", torii_db_path, suffix);
+
+        if !Path::new(&path).exists() {
+            continue;
+        }
+
+        if archive {
+            fs::rename(&path, format!("{}.{}{}", torii_db_path, timestamp, suffix))?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn follow_indexing(state: &ServerState, torii_db_path: &str, progress: &Progress) {
+    let mut last_reported = None;
+
+    while !progress.is_closed() {
+        if !state.supervisor.is_running(Service::Torii).await {
+            progress.error("Torii stopped while reindexing").await;
+            return;
+        }
+
+        let katana_head = match indexing::katana_head(&state.json_rpc_client).await {
+            Ok(katana_head) => katana_head,
+            Err(error) => {
+                progress.error(&format!("Failed to get Katana's head: {}", error)).await;
+                return;
+            }
+        };
+
+        // the database is empty until Torii indexed its first block
+        let torii_head = indexing::torii_head(torii_db_path).await.unwrap_or_default();
+
+        if torii_head >= katana_head {
+            progress.done(&format!("Torii caught up with Katana at block {}", katana_head)).await;
+            return;
+        }
+
+        if last_reported != Some(torii_head) {
+            progress.step(&format!("Indexed block {} of {}", torii_head, katana_head)).await;
+            last_reported = Some(torii_head);
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+async fn reindex(state: ServerState, torii_db_path: String, archive: bool, progress: Progress) {
+    let action = if archive { "archiving" } else { "deleting" };
+    progress.step(&format!("Stopping Torii and {} its database", action)).await;
+
+    let restarted = state.supervisor.restart_after(
+        Service::Torii,
+        state.config.torii_args(),
+        || remove_database(&torii_db_path, archive),
+    ).await;
+
+    if let Err(error) = restarted {
+        progress.error(&format!("Failed to reset Torii: {}", error)).await;
+        return;
+    }
+
+    progress.step("Torii restarted, reindexing from scratch").await;
+
+    follow_indexing(&state, &torii_db_path, &progress).await;
+}
+
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<Reindex>,
+) -> Response {
+    let Some(torii_db_path) = state.torii_db_path.clone() else {
+        return (StatusCode::CONFLICT, Json("Torii runs remotely (slot) and can't be reindexed")).into_response();
+    };
+
+    let (progress, stream) = Progress::channel();
+    tokio::spawn(reindex(state, torii_db_path, payload.archive, progress));
+
+    stream.into_response()
+}
@@ -0,0 +1,14 @@
+use axum::{Extension, Json};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+use crate::world_diff::{self, DiffRequest, WorldDiff};
+
+/**
+ * what changed between two exports of the world, or between an export and the live chain
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Json(payload): Json<DiffRequest>,
+) -> ApiResult<Json<WorldDiff>> {
+    Ok(Json(world_diff::diff_with(&state, payload).await?))
+}
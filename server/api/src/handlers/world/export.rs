@@ -0,0 +1,45 @@
+use axum::Extension;
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+use crate::world_export::{self, Source};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    #[default]
+    Json,
+    Cbor,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub source: Source,
+}
+
+/**
+ * every entity of every model as a file, `?format=cbor` for a smaller one
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    Query(query): Query<ExportQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let export = world_export::export(&state, query.source).await?;
+
+    let (content_type, body) = match query.format {
+        Format::Json => ("application/json", serde_json::to_vec(&export)?),
+        Format::Cbor => {
+            let mut body = vec![];
+            ciborium::into_writer(&export, &mut body)?;
+            ("application/cbor", body)
+        }
+    };
+
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, content_type)], body))
+}
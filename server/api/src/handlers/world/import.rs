@@ -0,0 +1,28 @@
+use axum::{Extension, Json};
+use axum::body::Bytes;
+use crate::error::{ApiError, ApiResult};
+use crate::server_state::ServerState;
+use crate::world_export::{self, Imported, WorldExport};
+
+/// The UTF-8 byte order mark some editors put in front of a JSON file.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/**
+ * writes a file of the export endpoint back into the world, in JSON or CBOR
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+    body: Bytes,
+) -> ApiResult<Json<Imported>> {
+    // hand-edited and pretty-printed files may start with a BOM or whitespace, CBOR files never do
+    let json = body.strip_prefix(BOM).unwrap_or(&body).trim_ascii_start();
+
+    let export: WorldExport = match json.first() {
+        Some(b'{') => serde_json::from_slice(json)
+            .map_err(|error| ApiError::bad_request(format!("invalid export: {}", error)))?,
+        _ => ciborium::from_reader(body.as_ref())
+            .map_err(|error| ApiError::bad_request(format!("invalid export: {}", error)))?,
+    };
+
+    Ok(Json(world_export::import(&state, &export).await?))
+}
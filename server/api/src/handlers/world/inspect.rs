@@ -0,0 +1,13 @@
+use axum::{Extension, Json};
+use crate::error::ApiResult;
+use crate::server_state::ServerState;
+use crate::world::{self, WorldReport};
+
+/**
+ * the world's models, contracts and getters, and whether they match the manifest
+ */
+pub async fn handler(
+    Extension(state): Extension<ServerState>,
+) -> ApiResult<Json<WorldReport>> {
+    Ok(Json(world::inspect(&state).await?))
+}
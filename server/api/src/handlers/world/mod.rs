@@ -0,0 +1,4 @@
+pub mod diff;
+pub mod export;
+pub mod import;
+pub mod inspect;
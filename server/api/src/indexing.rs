@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use jsonrpsee_core::client::ClientT;
+use jsonrpsee_core::params::ArrayParams;
+use jsonrpsee_http_client::HttpClient;
+use rusqlite::{Connection, OpenFlags};
+use crate::server_state::ServerState;
+
+/**
+ * the latest block number of Katana
+ */
+pub async fn katana_head(client: &HttpClient) -> Result<u64> {
+    let head = client.request::<u64, ArrayParams>("starknet_blockNumber", ArrayParams::default()).await?;
+    Ok(head)
+}
+
+/**
+ * the last block Torii indexed, read from its database
+ */
+pub async fn torii_head(torii_db_path: &str) -> Result<u64> {
+    let torii_db_path = torii_db_path.to_string();
+
+    let head = tokio::task::spawn_blocking(move || -> Result<u64> {
+        let connection = Connection::open_with_flags(torii_db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let head = connection.query_row("SELECT COALESCE(MAX(head), 0) FROM indexers", [], |row| row.get(0))?;
+        Ok(head)
+    }).await??;
+
+    Ok(head)
+}
+
+/**
+ * how many blocks Torii is behind Katana, None when Torii doesn't run locally
+ */
+pub async fn torii_lag(state: &ServerState) -> Result<Option<u64>> {
+    let Some(torii_db_path) = &state.torii_db_path else {
+        return Ok(None);
+    };
+
+    let katana_head = katana_head(&state.json_rpc_client).await?;
+    let torii_head = torii_head(torii_db_path).await?;
+
+    Ok(Some(katana_head.saturating_sub(torii_head)))
+}
+
+/**
+ * how many entities Torii indexed per model, by model name
+ */
+pub async fn entity_counts(torii_db_path: &str) -> Result<HashMap<String, u64>> {
+    let torii_db_path = torii_db_path.to_string();
+
+    let counts = tokio::task::spawn_blocking(move || -> Result<HashMap<String, u64>> {
+        let connection = Connection::open_with_flags(torii_db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut statement = connection.prepare(
+            "SELECT models.name, COUNT(entity_model.entity_id) FROM models \
+             LEFT JOIN entity_model ON entity_model.model_id = models.id \
+             GROUP BY models.name"
+        )?;
+        let counts = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    }).await??;
+
+    Ok(counts)
+}
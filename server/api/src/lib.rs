@@ -1,2 +1,23 @@
+pub mod abi;
+pub mod auth;
+pub mod chain;
+pub mod consistency;
+pub mod contract_class;
+pub mod contracts;
+pub mod error;
+pub mod genesis;
+pub mod genesis_builder;
 pub mod handlers;
-pub mod server_state;
\ No newline at end of file
+pub mod indexing;
+pub mod manifest;
+pub mod metrics;
+pub mod model_storage;
+pub mod node_config;
+pub mod progress;
+pub mod scenario;
+pub mod server_state;
+pub mod supervisor;
+pub mod torii;
+pub mod world;
+pub mod world_diff;
+pub mod world_export;
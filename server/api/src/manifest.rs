@@ -0,0 +1,234 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::types::contract::AbiStruct;
+use crate::abi::Abi;
+
+/// The deployment manifest sozo writes after migrating, only the parts Keiko looks at are typed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub world: ManifestContract,
+    #[serde(default)]
+    pub contracts: Vec<ManifestContract>,
+    #[serde(default)]
+    pub models: Vec<ManifestModel>,
+    /// the directory the ABI paths are resolved from
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestContract {
+    pub name: String,
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default)]
+    pub address: Option<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub class_hash: FieldElement,
+    /// inline, or a path to a JSON file
+    #[serde(default)]
+    pub abi: Value,
+    #[serde(default)]
+    pub reads: Vec<String>,
+    #[serde(default)]
+    pub writes: Vec<String>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestModel {
+    pub name: String,
+    #[serde_as(as = "UfeHex")]
+    pub class_hash: FieldElement,
+    #[serde(default)]
+    pub members: Vec<ModelMember>,
+    /// inline, or a path to a JSON file
+    #[serde(default)]
+    pub abi: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default)]
+    pub key: bool,
+}
+
+/// The name without its module path, `dojo_examples::actions::actions` is `actions`.
+pub fn short_name(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// The crate a contract or model is defined in, `dojo_examples::models::position` is in `dojo_examples`.
+pub fn namespace(name: &str) -> &str {
+    name.split("::").next().unwrap_or(name)
+}
+
+/// A contract given by address rather than by name, only 0x prefixed ones are: `add` or `beef` are names.
+pub fn literal_address(contract: &str) -> Option<FieldElement> {
+    contract.starts_with("0x").then(|| FieldElement::from_hex_be(contract).ok()).flatten()
+}
+
+impl ManifestModel {
+    /**
+     * the model's struct in its ABI, the one with the model's members
+     */
+    pub fn find_struct<'a>(&self, abi: &'a Abi) -> anyhow::Result<&'a AbiStruct> {
+        // the manifest only has the short type names, the ABI has the model's struct with full ones
+        abi.structs()
+            .find(|abi_struct| abi_struct.members.iter().map(|member| &member.name).eq(self.members.iter().map(|member| &member.name)))
+            .ok_or_else(|| anyhow!("the ABI of {} has no struct with the model's members", self.name))
+    }
+}
+
+impl Manifest {
+    /**
+     * Reads the deployment manifest from the manifest dir, wherever the Dojo version put it:
+     * `manifest.json`, `deployments/<chain>.json` or `<profile>/manifest.json`.
+     */
+    pub fn load(manifest_base_dir: &str) -> anyhow::Result<Self> {
+        let base_dir = Path::new(manifest_base_dir);
+        let path = find_manifest(base_dir)
+            .ok_or_else(|| anyhow!("no deployment manifest in {}, has the world been migrated?", manifest_base_dir))?;
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut manifest: Manifest = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        manifest.base_dir = base_dir.to_path_buf();
+        Ok(manifest)
+    }
+
+    /**
+     * where the deployment manifest is, None before the world is migrated
+     */
+    pub fn path(manifest_base_dir: &str) -> Option<PathBuf> {
+        find_manifest(Path::new(manifest_base_dir))
+    }
+
+    /**
+     * the world and the contracts, the world first
+     */
+    pub fn all_contracts(&self) -> impl Iterator<Item = &ManifestContract> {
+        std::iter::once(&self.world).chain(self.contracts.iter())
+    }
+
+    /**
+     * finds a contract by its full name, its short name or its 0x prefixed address, names first
+     */
+    pub fn find_contract(&self, contract: &str) -> anyhow::Result<Option<&ManifestContract>> {
+        if let Some(found) = self.all_contracts().find(|manifest_contract| manifest_contract.name == contract) {
+            return Ok(Some(found));
+        }
+
+        let matches: Vec<_> = self.all_contracts()
+            .filter(|manifest_contract| short_name(&manifest_contract.name) == contract)
+            .collect();
+
+        match matches.as_slice() {
+            [] => match literal_address(contract) {
+                Some(address) => Ok(self.all_contracts().find(|manifest_contract| manifest_contract.address == Some(address))),
+                None => Ok(None),
+            },
+            [found] => Ok(Some(found)),
+            _ => Err(anyhow!(
+                "`{}` is ambiguous, use one of {}",
+                contract,
+                matches.iter().map(|found| found.name.as_str()).collect::<Vec<_>>().join(", ")
+            ))
+        }
+    }
+
+    /**
+     * finds a model by its full name, its short name or its struct name
+     */
+    pub fn find_model(&self, model: &str) -> Option<&ManifestModel> {
+        self.models.iter().find(|manifest_model| manifest_model.name == model)
+            .or_else(|| self.models.iter().find(|manifest_model| short_name(&manifest_model.name) == model))
+            .or_else(|| self.models.iter().find(|manifest_model| self.struct_name(manifest_model).is_ok_and(|name| name == model)))
+    }
+
+    /**
+     * the name of the model's struct, from its ABI. Dojo derives the model's selector from it
+     */
+    pub fn struct_name(&self, model: &ManifestModel) -> anyhow::Result<String> {
+        let abi = self.model_abi(model)?;
+        Ok(short_name(&model.find_struct(&abi)?.name).to_string())
+    }
+
+    /**
+     * the ABI of a contract, read from its file when the manifest only has the path
+     */
+    pub fn abi(&self, contract: &ManifestContract) -> anyhow::Result<Abi> {
+        self.load_abi(&contract.name, &contract.abi)
+    }
+
+    /**
+     * the ABI of a model, which has the model's struct in it
+     */
+    pub fn model_abi(&self, model: &ManifestModel) -> anyhow::Result<Abi> {
+        self.load_abi(&model.name, &model.abi)
+    }
+
+    fn load_abi(&self, name: &str, abi: &Value) -> anyhow::Result<Abi> {
+        match abi {
+            Value::String(path) => {
+                let path = self.resolve(path)
+                    .ok_or_else(|| anyhow!("ABI of {} not found at {}", name, path))?;
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+
+                Abi::parse(serde_json::from_str(&contents)?)
+            }
+            Value::Array(_) => Abi::parse(abi.clone()),
+            _ => Err(anyhow!("the manifest has no ABI for {}", name))
+        }
+    }
+
+    /// ABI paths are relative to the project root, which has the manifest dir in it.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let candidates = [
+            self.base_dir.parent().map(|root| root.join(path)),
+            path.strip_prefix("manifests/").map(|path| self.base_dir.join(path)),
+            Some(self.base_dir.join(path)),
+        ];
+
+        candidates.into_iter().flatten().find(|candidate| candidate.is_file())
+    }
+}
+
+fn find_manifest(base_dir: &Path) -> Option<PathBuf> {
+    let manifest = base_dir.join("manifest.json");
+    if manifest.is_file() {
+        return Some(manifest);
+    }
+
+    let json_files = |dir: PathBuf| -> Vec<PathBuf> {
+        let mut files: Vec<_> = fs::read_dir(dir).into_iter().flatten().flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        files.sort();
+        files
+    };
+
+    if let Some(deployment) = json_files(base_dir.join("deployments")).into_iter().next() {
+        return Some(deployment);
+    }
+
+    let mut profiles: Vec<_> = fs::read_dir(base_dir).into_iter().flatten().flatten()
+        .map(|entry| entry.path().join("manifest.json"))
+        .filter(|manifest| manifest.is_file())
+        .collect();
+    profiles.sort();
+    profiles.into_iter().next()
+}
@@ -0,0 +1,95 @@
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use prometheus::{IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use crate::server_state::ServerState;
+
+/// Keiko's own metrics, served at `/metrics` next to the ones proxied from Katana.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub api_requests: IntCounterVec,
+    pub blocks_mined: IntCounter,
+    pub funding_operations: IntCounterVec,
+    pub child_restarts: IntCounterVec,
+    pub torii_indexing_lag: IntGauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new_custom(Some("keiko".to_string()), None)
+            .expect("valid registry");
+
+        let api_requests = IntCounterVec::new(
+            Opts::new("api_requests_total", "API requests per handler"),
+            &["handler", "status"],
+        ).expect("valid metric");
+
+        let blocks_mined = IntCounter::new(
+            "blocks_mined_total",
+            "Blocks mined through /api/block",
+        ).expect("valid metric");
+
+        let funding_operations = IntCounterVec::new(
+            Opts::new("funding_operations_total", "Funding operations, `eth` or `other` tokens"),
+            &["token"],
+        ).expect("valid metric");
+
+        let child_restarts = IntCounterVec::new(
+            Opts::new("child_restarts_total", "Restarts of Katana and Torii"),
+            &["service"],
+        ).expect("valid metric");
+
+        let torii_indexing_lag = IntGauge::new(
+            "torii_indexing_lag_blocks",
+            "Katana's head block minus the last block Torii indexed",
+        ).expect("valid metric");
+
+        registry.register(Box::new(api_requests.clone())).expect("unique metric");
+        registry.register(Box::new(blocks_mined.clone())).expect("unique metric");
+        registry.register(Box::new(funding_operations.clone())).expect("unique metric");
+        registry.register(Box::new(child_restarts.clone())).expect("unique metric");
+        registry.register(Box::new(torii_indexing_lag.clone())).expect("unique metric");
+
+        Self {
+            registry,
+            api_requests,
+            blocks_mined,
+            funding_operations,
+            child_restarts,
+            torii_indexing_lag,
+        }
+    }
+}
+
+impl Metrics {
+    /**
+     * renders the metrics in the Prometheus text format
+     */
+    pub fn encode(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .expect("able to encode metrics")
+    }
+}
+
+/**
+ * counts the requests per matched route, use it as a route layer
+ */
+pub async fn track_requests<B>(request: Request<B>, next: Next<B>) -> Response {
+    let handler = request.extensions().get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let metrics = request.extensions().get::<ServerState>().map(|state| state.metrics.clone());
+
+    let response = next.run(request).await;
+
+    if let Some(metrics) = metrics {
+        metrics.api_requests
+            .with_label_values(&[&handler, response.status().as_str()])
+            .inc();
+    }
+
+    response
+}
@@ -0,0 +1,468 @@
+use anyhow::{anyhow, bail, Context};
+use num_bigint::BigUint;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+use starknet_crypto::poseidon_hash_many;
+use crate::abi::Abi;
+use crate::manifest::{self, ManifestModel};
+
+/// How many bits of a storage slot Dojo packs values into, all 252 would allow invalid felts.
+const PACKING_MAX_BITS: u32 = 251;
+
+/// Where a member's felt lives in the world's storage.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PackedField {
+    /// offset from the entity's base address
+    pub slot: usize,
+    pub bit_offset: u32,
+    pub bits: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub key: bool,
+    /// empty for keys, they aren't stored
+    pub fields: Vec<PackedField>,
+}
+
+/// How a model's values are packed in the world's storage: the non-key members, serialized,
+/// packed into as few 251 bit slots as possible, starting at
+/// `poseidon('dojo_storage', model selector, poseidon(keys))`.
+pub struct ModelLayout {
+    pub selector: FieldElement,
+    /// the model's struct, without its module path
+    pub struct_name: String,
+    pub members: Vec<LayoutMember>,
+    pub slot_count: usize,
+    abi: Abi,
+}
+
+/// The storage of one entity of a model.
+#[serde_as]
+#[derive(Serialize)]
+pub struct EntityStorage {
+    #[serde_as(as = "UfeHex")]
+    pub entity_id: FieldElement,
+    #[serde_as(as = "UfeHex")]
+    pub base_address: FieldElement,
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub slots: Vec<FieldElement>,
+    /// the serialized keys, the entity id is their hash
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub keys: Vec<FieldElement>,
+}
+
+impl ModelLayout {
+    /**
+     * the layout of a model, from the struct in the model's ABI
+     */
+    pub fn new(model: &ManifestModel, abi: Abi) -> anyhow::Result<Self> {
+        let model_struct = model.find_struct(&abi)?.clone();
+        let struct_name = manifest::short_name(&model_struct.name).to_string();
+
+        let mut members = vec![];
+        let mut packer = Packer::default();
+
+        for (member, manifest_member) in model_struct.members.iter().zip(&model.members) {
+            let fields = if manifest_member.key {
+                vec![]
+            } else {
+                abi.packed_layout(&member.r#type)
+                    .with_context(|| format!("can't pack {}", member.name))?
+                    .into_iter()
+                    .map(|bits| packer.place(bits))
+                    .collect()
+            };
+
+            members.push(LayoutMember {
+                name: member.name.clone(),
+                ty: member.r#type.clone(),
+                key: manifest_member.key,
+                fields,
+            });
+        }
+
+        Ok(Self {
+            selector: get_selector_from_name(&struct_name)?,
+            struct_name,
+            members,
+            slot_count: packer.slot_count(),
+            abi,
+        })
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &LayoutMember> {
+        self.members.iter().filter(|member| member.key)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &LayoutMember> {
+        self.members.iter().filter(|member| !member.key)
+    }
+
+    /**
+     * the entity's id and storage addresses, from its keys given as an array or an object by name
+     */
+    pub fn entity(&self, keys: &Value) -> anyhow::Result<EntityStorage> {
+        let key_members: Vec<_> = self.keys().collect();
+
+        let key_values: Vec<&Value> = match keys {
+            Value::Array(keys) if keys.len() == key_members.len() => keys.iter().collect(),
+            Value::Object(keys) => key_members.iter()
+                .map(|member| keys.get(&member.name).ok_or_else(|| anyhow!("missing key `{}`", member.name)))
+                .collect::<anyhow::Result<_>>()?,
+            key if key_members.len() == 1 => vec![key],
+            _ => bail!("expected {} keys: {}", key_members.len(), key_members.iter().map(|member| member.name.as_str()).collect::<Vec<_>>().join(", "))
+        };
+
+        let mut serialized_keys = vec![];
+        for (member, value) in key_members.iter().zip(key_values) {
+            self.abi.encode(&member.ty, value, &mut serialized_keys)
+                .with_context(|| format!("invalid key `{}`", member.name))?;
+        }
+
+        let entity_id = poseidon_hash_many(&serialized_keys);
+        let base_address = storage_base_address(poseidon_hash_many(&[
+            cairo_short_string_to_felt("dojo_storage")?,
+            self.selector,
+            entity_id,
+        ]));
+
+        Ok(EntityStorage {
+            entity_id,
+            base_address,
+            slots: (0..self.slot_count).map(|slot| base_address + FieldElement::from(slot)).collect(),
+            keys: serialized_keys,
+        })
+    }
+
+    /**
+     * decodes the members out of the entity's slots
+     */
+    pub fn decode(&self, slots: &[FieldElement]) -> anyhow::Result<Value> {
+        let mut values = Map::new();
+
+        for member in self.values() {
+            let mut felts = member.fields.iter().map(|field| unpack(slots, field));
+            values.insert(member.name.clone(), self.abi.decode(&member.ty, &mut felts)?);
+        }
+
+        Ok(Value::Object(values))
+    }
+
+    /**
+     * packs the given members into the entity's slots, leaving the other members as they are
+     */
+    pub fn encode(&self, slots: &mut [FieldElement], values: &Map<String, Value>) -> anyhow::Result<()> {
+        for name in values.keys() {
+            if !self.values().any(|member| &member.name == name) {
+                bail!("`{}` is not a value of the model, keys can't be set", name);
+            }
+        }
+
+        for member in self.values() {
+            let Some(value) = values.get(&member.name) else {
+                continue;
+            };
+
+            let mut felts = vec![];
+            self.abi.encode(&member.ty, value, &mut felts)
+                .with_context(|| format!("invalid value for `{}`", member.name))?;
+
+            for (field, felt) in member.fields.iter().zip(felts) {
+                pack(slots, field, felt)
+                    .with_context(|| format!("invalid value for `{}`", member.name))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * members, keys or values, in the shape `decode` gives, so `"0x2a"` and `42` are the same u32.
+     * Members the model doesn't have and values that don't fit their type are kept as written
+     */
+    pub fn normalize(&self, values: &Map<String, Value>) -> Map<String, Value> {
+        values.iter()
+            .map(|(name, value)| {
+                let normalized = self.members.iter()
+                    .find(|member| &member.name == name)
+                    .and_then(|member| self.abi.normalize(&member.ty, value).ok())
+                    .unwrap_or_else(|| value.clone());
+                (name.clone(), normalized)
+            })
+            .collect()
+    }
+
+    /**
+     * the values' fields unpacked from the slots, with the bits of each, which is what the world's `set_entity` takes
+     */
+    pub fn unpacked(&self, slots: &[FieldElement]) -> (Vec<FieldElement>, Vec<u8>) {
+        self.values()
+            .flat_map(|member| &member.fields)
+            .map(|field| (unpack(slots, field), field.bits))
+            .unzip()
+    }
+
+    /**
+     * the members whose stored value differs from the expected one, compared once serialized
+     * so `"0x2a"`, `42` and `"42"` are the same felt
+     */
+    pub fn mismatches(&self, slots: &[FieldElement], expected: &Map<String, Value>) -> anyhow::Result<Vec<String>> {
+        let mut expected_slots = slots.to_vec();
+        self.encode(&mut expected_slots, expected)?;
+
+        Ok(self.values()
+            .filter(|member| expected.contains_key(&member.name))
+            .filter(|member| member.fields.iter().any(|field| unpack(slots, field) != unpack(&expected_slots, field)))
+            .map(|member| member.name.clone())
+            .collect())
+    }
+}
+
+/// Dojo's packing: a value goes in the current slot if it fits, otherwise it starts the next one.
+#[derive(Default)]
+struct Packer {
+    slot: usize,
+    bit_offset: u32,
+    placed: bool,
+}
+
+impl Packer {
+    fn place(&mut self, bits: u8) -> PackedField {
+        if PACKING_MAX_BITS - self.bit_offset < bits as u32 {
+            self.slot += 1;
+            self.bit_offset = 0;
+        }
+
+        let field = PackedField {
+            slot: self.slot,
+            bit_offset: self.bit_offset,
+            bits,
+        };
+        self.bit_offset += bits as u32;
+        self.placed = true;
+
+        field
+    }
+
+    fn slot_count(&self) -> usize {
+        if self.placed { self.slot + 1 } else { 0 }
+    }
+}
+
+/// `storage_base_address_from_felt252`, which keeps the address below 2**251 - 256.
+fn storage_base_address(address: FieldElement) -> FieldElement {
+    let bound = (BigUint::from(1u8) << 251u32) - 256u32;
+    let address = BigUint::from_bytes_be(&address.to_bytes_be()) % bound;
+
+    FieldElement::from_byte_slice_be(&address.to_bytes_be()).expect("below the bound")
+}
+
+fn mask(bits: u8) -> BigUint {
+    (BigUint::from(1u8) << bits as u32) - 1u8
+}
+
+fn unpack(slots: &[FieldElement], field: &PackedField) -> FieldElement {
+    let slot = BigUint::from_bytes_be(&slots[field.slot].to_bytes_be());
+    let value = (slot >> field.bit_offset) & mask(field.bits);
+
+    FieldElement::from_byte_slice_be(&value.to_bytes_be()).expect("at most 251 bits")
+}
+
+fn pack(slots: &mut [FieldElement], field: &PackedField, felt: FieldElement) -> anyhow::Result<()> {
+    let value = BigUint::from_bytes_be(&felt.to_bytes_be());
+    if value.bits() > field.bits as u64 {
+        bail!("{:#x} doesn't fit in {} bits", felt, field.bits);
+    }
+
+    let slot = BigUint::from_bytes_be(&slots[field.slot].to_bytes_be());
+    let previous = (&slot >> field.bit_offset) & mask(field.bits);
+    let packed = slot - (previous << field.bit_offset) + (value << field.bit_offset);
+
+    slots[field.slot] = FieldElement::from_byte_slice_be(&packed.to_bytes_be())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    const DIRECTION: &str = "dojo_examples::models::Direction";
+
+    /// A model of the dojo starter, with the ABI sozo writes in the manifest: `moves` is the `Moves` struct.
+    fn layout(name: &str, members: &[(&str, &str, bool)]) -> ModelLayout {
+        let short_name = manifest::short_name(name);
+        let struct_name = format!("{}{}", short_name[..1].to_uppercase(), &short_name[1..]);
+        let abi = json!([
+            {
+                "type": "struct",
+                "name": format!("dojo_examples::models::{}", struct_name),
+                "members": members.iter().map(|(name, ty, _)| json!({ "name": name, "type": ty })).collect::<Vec<_>>()
+            },
+            {
+                "type": "enum",
+                "name": DIRECTION,
+                "variants": [
+                    { "name": "None", "type": "()" },
+                    { "name": "Left", "type": "()" },
+                    { "name": "Right", "type": "()" }
+                ]
+            }
+        ]);
+        let model: ManifestModel = serde_json::from_value(json!({
+            "name": name,
+            "class_hash": "0x1",
+            "members": members.iter().map(|(name, ty, key)| json!({ "name": name, "type": ty, "key": key })).collect::<Vec<_>>(),
+            "abi": abi
+        })).unwrap();
+
+        ModelLayout::new(&model, Abi::parse(abi).unwrap()).unwrap()
+    }
+
+    fn moves() -> ModelLayout {
+        layout("dojo_examples::models::moves", &[
+            ("player", "core::starknet::contract_address::ContractAddress", true),
+            ("remaining", "core::integer::u8", false),
+            ("last_direction", DIRECTION, false),
+            ("can_move", "core::bool", false),
+        ])
+    }
+
+    fn fields(layout: &ModelLayout, member: &str) -> Vec<(usize, u32, u8)> {
+        layout.members.iter().find(|candidate| candidate.name == member).unwrap()
+            .fields.iter().map(|field| (field.slot, field.bit_offset, field.bits)).collect()
+    }
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn packs_values_low_bits_first() {
+        let layout = moves();
+
+        assert_eq!(layout.struct_name, "Moves");
+        assert_eq!(layout.selector, get_selector_from_name("Moves").unwrap());
+        assert_eq!(layout.slot_count, 1);
+        assert!(fields(&layout, "player").is_empty());
+        assert_eq!(fields(&layout, "remaining"), [(0, 0, 8)]);
+        assert_eq!(fields(&layout, "last_direction"), [(0, 8, 8)]);
+        assert_eq!(fields(&layout, "can_move"), [(0, 16, 1)]);
+
+        let mut slots = vec![FieldElement::ZERO];
+        layout.encode(&mut slots, &object(json!({ "remaining": 10, "last_direction": "Right", "can_move": true }))).unwrap();
+        assert_eq!(slots, [FieldElement::from(10u32 + (2 << 8) + (1 << 16))]);
+    }
+
+    #[test]
+    fn starts_a_new_slot_when_a_value_doesnt_fit() {
+        let layout = layout("dojo_examples::models::balance", &[
+            ("owner", "core::starknet::contract_address::ContractAddress", true),
+            ("token", "core::felt252", false),
+            ("decimals", "core::integer::u8", false),
+            ("amount", "core::integer::u256", false),
+        ]);
+
+        assert_eq!(fields(&layout, "token"), [(0, 0, 251)]);
+        assert_eq!(fields(&layout, "decimals"), [(1, 0, 8)]);
+        // the low half still fits after the u8, the high half doesn't
+        assert_eq!(fields(&layout, "amount"), [(1, 8, 128), (2, 0, 128)]);
+        assert_eq!(layout.slot_count, 3);
+    }
+
+    #[test]
+    fn stores_entities_at_dojo_storage_addresses() {
+        let layout = moves();
+        let player = FieldElement::from_hex_be("0x517ececd29116499f4a1b64b094da79ba08dfd54a3edaa316134c41f8160973").unwrap();
+
+        let storage = layout.entity(&json!({ "player": format!("{:#x}", player) })).unwrap();
+
+        // poseidon('dojo_storage', selector, poseidon(keys)), then `storage_base_address_from_felt252`
+        let entity_id = poseidon_hash_many(&[player]);
+        let address = poseidon_hash_many(&[cairo_short_string_to_felt("dojo_storage").unwrap(), get_selector_from_name("Moves").unwrap(), entity_id]);
+        let bound = (BigUint::from(1u8) << 251u32) - 256u32;
+        let base_address = BigUint::from_bytes_be(&address.to_bytes_be()) % bound;
+
+        assert_eq!(storage.keys, [player]);
+        assert_eq!(storage.entity_id, entity_id);
+        assert_eq!(BigUint::from_bytes_be(&storage.base_address.to_bytes_be()), base_address);
+        assert_eq!(storage.slots, [storage.base_address]);
+
+        // pinned, a change to how keys are serialized or hashed moves every entity
+        assert_eq!(format!("{:#x}", storage.entity_id), "0x28cd7ee02d7f6ec9810e75b930e8e607793b302445abbdee0ac88143f18da20");
+        assert_eq!(format!("{:#x}", storage.base_address), "0x7dd7a8f9a974389b3a99f5951ffbd35f0f3c40f45f8f03dcfdd3a7ce7bf9590");
+
+        // the keys as an array, or the only key alone
+        assert_eq!(layout.entity(&json!([format!("{:#x}", player)])).unwrap().base_address, storage.base_address);
+        assert_eq!(layout.entity(&json!(format!("{:#x}", player))).unwrap().base_address, storage.base_address);
+        assert!(layout.entity(&json!({})).is_err());
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let layout = layout("dojo_examples::models::balance", &[
+            ("owner", "core::starknet::contract_address::ContractAddress", true),
+            ("token", "core::felt252", false),
+            ("decimals", "core::integer::u8", false),
+            ("amount", "core::integer::u256", false),
+        ]);
+        let values = json!({
+            "token": "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+            "decimals": 18,
+            "amount": "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        });
+
+        let mut slots = vec![FieldElement::ZERO; layout.slot_count];
+        layout.encode(&mut slots, &object(values.clone())).unwrap();
+        assert_eq!(layout.decode(&slots).unwrap(), values);
+
+        // a partial write leaves the other members as they are
+        layout.encode(&mut slots, &object(json!({ "decimals": 6 }))).unwrap();
+        assert_eq!(layout.decode(&slots).unwrap()["decimals"], json!(6));
+        assert_eq!(layout.decode(&slots).unwrap()["amount"], values["amount"]);
+    }
+
+    #[test]
+    fn unpacks_the_fields_set_entity_takes() {
+        let layout = moves();
+        let mut slots = vec![FieldElement::ZERO];
+        layout.encode(&mut slots, &object(json!({ "remaining": 10, "last_direction": "Right", "can_move": true }))).unwrap();
+
+        let (fields, bits) = layout.unpacked(&slots);
+        assert_eq!(fields, [FieldElement::from(10u8), FieldElement::TWO, FieldElement::ONE]);
+        assert_eq!(bits, [8, 8, 1]);
+    }
+
+    #[test]
+    fn rejects_keys_and_values_that_dont_fit() {
+        let layout = moves();
+        let mut slots = vec![FieldElement::ZERO];
+
+        assert!(layout.encode(&mut slots, &object(json!({ "player": "0x1" }))).is_err());
+        assert!(layout.encode(&mut slots, &object(json!({ "remaining": 256 }))).is_err());
+        assert!(layout.encode(&mut slots, &object(json!({ "unknown": 1 }))).is_err());
+        assert_eq!(slots, [FieldElement::ZERO]);
+    }
+
+    #[test]
+    fn compares_values_once_serialized() {
+        let layout = moves();
+        let mut slots = vec![FieldElement::ZERO];
+        layout.encode(&mut slots, &object(json!({ "remaining": 42, "last_direction": "Left", "can_move": false }))).unwrap();
+
+        assert!(layout.mismatches(&slots, &object(json!({ "remaining": "0x2a", "can_move": false }))).unwrap().is_empty());
+        assert_eq!(layout.mismatches(&slots, &object(json!({ "last_direction": "Right" }))).unwrap(), ["last_direction"]);
+        assert_eq!(layout.normalize(&object(json!({ "remaining": "0x2a", "other": "0x2a" }))), object(json!({ "remaining": 42, "other": "0x2a" })));
+    }
+}
@@ -0,0 +1,55 @@
+use std::convert::Infallible;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+pub type ProgressStream = Sse<ReceiverStream<Result<Event, Infallible>>>;
+
+#[derive(Serialize)]
+struct Message<'a> {
+    message: &'a str,
+}
+
+/// Reports the progress of a long running operation as server-sent events: any number of
+/// `progress` events, followed by a single `done` or `error` event.
+#[derive(Clone)]
+pub struct Progress {
+    sender: mpsc::Sender<Result<Event, Infallible>>,
+}
+
+impl Progress {
+    pub fn channel() -> (Progress, ProgressStream) {
+        let (sender, receiver) = mpsc::channel(16);
+        let stream = Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default());
+
+        (Progress { sender }, stream)
+    }
+
+    pub async fn step(&self, message: &str) {
+        self.send("progress", message).await
+    }
+
+    pub async fn done(&self, message: &str) {
+        self.send("done", message).await
+    }
+
+    pub async fn error(&self, message: &str) {
+        self.send("error", message).await
+    }
+
+    /// whether the client went away
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    async fn send(&self, event: &str, message: &str) {
+        let event = Event::default()
+            .event(event)
+            .json_data(Message { message })
+            .expect("able to serialize message");
+
+        // the client may have gone away, the operation carries on regardless
+        let _ = self.sender.send(Ok(event)).await;
+    }
+}
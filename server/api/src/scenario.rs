@@ -0,0 +1,168 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::contracts::impersonate::{self, Impersonate};
+use crate::handlers::contracts::invoke::{self, Invoke};
+use crate::handlers::katana::block;
+use crate::handlers::katana::funds::{self, FundAddress};
+use crate::handlers::storage::model::{self, Entity};
+use crate::server_state::ServerState;
+
+/// A reproducible game situation: steps run in order against the running world, see
+/// `samples/scenarios` for the format.
+#[derive(Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+pub struct Step {
+    /// shown in the report, the action's name otherwise
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Fund(FundAddress),
+    /// how many blocks
+    Mine(u64),
+    /// seconds
+    IncreaseTime(u64),
+    /// unix timestamp of the next block
+    SetTimestamp(u64),
+    Invoke(Invoke),
+    Impersonate(Impersonate),
+    Assert(Assertion),
+}
+
+/// The members of an entity that must have the given values, the others aren't checked.
+#[derive(Deserialize)]
+pub struct Assertion {
+    #[serde(flatten)]
+    entity: Entity,
+    values: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub name: Option<String>,
+    pub passed: bool,
+    /// the steps that ran, the failed one last
+    pub steps: Vec<StepReport>,
+}
+
+#[derive(Serialize)]
+pub struct StepReport {
+    pub index: usize,
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Fund(_) => "fund",
+            Action::Mine(_) => "mine",
+            Action::IncreaseTime(_) => "increase_time",
+            Action::SetTimestamp(_) => "set_timestamp",
+            Action::Invoke(_) => "invoke",
+            Action::Impersonate(_) => "impersonate",
+            Action::Assert(_) => "assert",
+        }
+    }
+}
+
+impl Scenario {
+    /**
+     * parses a scenario written in JSON or TOML
+     */
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        if contents.trim_start().starts_with('{') {
+            return serde_json::from_str(contents).map_err(|error| anyhow!("invalid JSON scenario: {}", error));
+        }
+
+        toml::from_str(contents).map_err(|error| anyhow!("invalid TOML scenario: {}", error))
+    }
+
+    /**
+     * runs the steps in order and stops at the first one that fails
+     */
+    pub async fn run(&self, state: &ServerState) -> Report {
+        let mut steps = vec![];
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let outcome = run_step(state, &step.action).await;
+            let ok = outcome.is_ok();
+
+            steps.push(StepReport {
+                index,
+                name: step.name.clone().unwrap_or_else(|| step.action.name().to_string()),
+                ok,
+                result: outcome.as_ref().ok().cloned(),
+                error: outcome.err().map(|error| error.message().to_string()),
+            });
+
+            if !ok {
+                break;
+            }
+        }
+
+        Report {
+            name: self.name.clone(),
+            passed: steps.iter().all(|step| step.ok),
+            steps,
+        }
+    }
+}
+
+async fn run_step(state: &ServerState, action: &Action) -> ApiResult<Value> {
+    let client = &state.json_rpc_client;
+    let metrics = &state.metrics;
+
+    let result = match action {
+        Action::Fund(payload) => serde_json::to_value(funds::fund(state, payload).await?)?,
+        Action::Mine(blocks) => Value::String(block::mine_block(*blocks, client, metrics).await?),
+        Action::IncreaseTime(seconds) => Value::String(block::increase_block_time(*seconds, client, metrics).await?),
+        Action::SetTimestamp(timestamp) => Value::String(block::set_block_timestamp(*timestamp, client, metrics).await?),
+        Action::Invoke(payload) => serde_json::to_value(invoke::invoke(state, payload).await?)?,
+        Action::Impersonate(payload) => serde_json::to_value(impersonate::impersonate(state, payload).await?)?,
+        Action::Assert(assertion) => assert(state, assertion).await?,
+    };
+
+    Ok(result)
+}
+
+/**
+ * compares the entity's stored values with the expected ones, answers with the stored values
+ */
+async fn assert(state: &ServerState, assertion: &Assertion) -> ApiResult<Value> {
+    let (model, layout) = model::layout(state, &assertion.entity.model)?;
+    let storage = layout.entity(&assertion.entity.keys)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+    let raw = model::read(state, model::world_address(state)?, &storage).await?;
+
+    let values = layout.decode(&raw)?;
+    let mismatches = layout.mismatches(&raw, &assertion.values)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    if !mismatches.is_empty() {
+        let found: Vec<String> = mismatches.iter()
+            .map(|member| format!("{} is {}", member, values[member]))
+            .collect();
+        return Err(ApiError::bad_request(format!("{} doesn't match: {}", model, found.join(", "))));
+    }
+
+    Ok(values)
+}
@@ -1,11 +1,43 @@
 use jsonrpsee_http_client::HttpClient;
 use url::Url;
+use std::sync::{Arc, Mutex};
+use crate::metrics::Metrics;
+use crate::node_config::ConfigSource;
+use crate::supervisor::Supervisor;
 
 #[derive(Clone)]
 pub struct ServerState {
     pub json_rpc_client: HttpClient,
     pub rpc_url: Url,
     pub manifest_base_dir: String,
+    pub storage_dir: String,
     pub world_address: String,
     pub torii_url: Url,
+    /// the database of the local Torii
+    pub torii_db_path: Option<String>,
+    /// where the local Katana serves its Prometheus metrics
+    pub katana_metrics_url: Option<Url>,
+    pub config: Arc<dyn ConfigSource>,
+    pub supervisor: Supervisor,
+    pub metrics: Metrics,
+    /// held while burners.json or impersonated.json is read, changed and written back
+    pub accounts_file_lock: Arc<Mutex<()>>,
+    /// held from validating a Katana update until Katana runs with it, or didn't take it
+    pub katana_update_lock: Arc<tokio::sync::Mutex<()>>,
+    /// held while token balances are read, added to and written back
+    pub funding_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl ServerState {
+    pub fn genesis_path(&self) -> String {
+        format!("{}/config/genesis.json", self.storage_dir)
+    }
+
+    pub fn burners_path(&self) -> String {
+        format!("{}/config/burners.json", self.storage_dir)
+    }
+
+    pub fn impersonated_path(&self) -> String {
+        format!("{}/config/impersonated.json", self.storage_dir)
+    }
 }
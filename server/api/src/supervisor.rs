@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::net::SocketAddr;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use nix::errno::Errno;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use crate::metrics::Metrics;
+
+/// How long a child gets to exit after SIGTERM before it is killed.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a child gets to listen on its address before it is killed.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The child processes Keiko runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Service {
+    Katana,
+    Torii,
+}
+
+impl Service {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Katana => "katana",
+            Service::Torii => "torii",
+        }
+    }
+}
+
+/// Everything needed to (re)start a child process.
+#[derive(Debug, Clone)]
+pub struct ChildSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub log_path: String,
+    /// the address the child listens on once it is ready
+    pub addr: SocketAddr,
+}
+
+/// How a restart that may roll back ended.
+#[derive(Debug)]
+pub enum Restarted {
+    /// the child runs with the new args
+    Started,
+    /// the child didn't come up with the new args and runs with the previous ones again
+    RolledBack(io::Error),
+}
+
+struct RunningChild {
+    spec: ChildSpec,
+    process: Child,
+}
+
+/// Owns the Katana and Torii processes, so they can be restarted and stopped cleanly.
+#[derive(Clone)]
+pub struct Supervisor {
+    children: Arc<Mutex<HashMap<Service, RunningChild>>>,
+    /// serializes starts, stops and restarts
+    operation: Arc<Mutex<()>>,
+    metrics: Metrics,
+}
+
+impl Supervisor {
+    pub fn new(metrics: Metrics) -> Self {
+        Self {
+            children: Arc::default(),
+            operation: Arc::default(),
+            metrics,
+        }
+    }
+
+    /**
+     * starts a child and waits until it listens on its address
+     */
+    pub async fn start(&self, service: Service, spec: ChildSpec) -> io::Result<()> {
+        let _operation = self.operation.lock().await;
+        self.spawn(service, spec, false).await
+    }
+
+    /**
+     * stops a child, returns the spec it was started with
+     */
+    pub async fn stop(&self, service: Service) -> io::Result<Option<ChildSpec>> {
+        let _operation = self.operation.lock().await;
+        self.terminate(service).await
+    }
+
+    /**
+     * stops a child and starts it again with new args
+     */
+    pub async fn restart(&self, service: Service, args: Vec<String>) -> io::Result<()> {
+        self.restart_after(service, args, || Ok(())).await
+    }
+
+    /**
+     * stops a child, runs `between` while it is down, and starts it again with new args
+     */
+    pub async fn restart_after<F>(&self, service: Service, args: Vec<String>, between: F) -> io::Result<()>
+        where F: FnOnce() -> io::Result<()>
+    {
+        let _operation = self.operation.lock().await;
+
+        let mut spec = self.terminate(service).await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{:?} is not running", service)))?;
+        spec.args = args;
+
+        // the child comes back either way
+        let between = between();
+
+        self.metrics.child_restarts.with_label_values(&[service.name()]).inc();
+        self.spawn(service, spec, true).await?;
+
+        between
+    }
+
+    /**
+     * stops a child and starts it again with new args, or with its previous args when it doesn't come
+     * up with the new ones
+     */
+    pub async fn restart_or_rollback(&self, service: Service, args: Vec<String>) -> io::Result<Restarted> {
+        let _operation = self.operation.lock().await;
+
+        let previous = self.terminate(service).await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{:?} is not running", service)))?;
+        let spec = ChildSpec { args, ..previous.clone() };
+
+        self.metrics.child_restarts.with_label_values(&[service.name()]).inc();
+        let Err(error) = self.spawn(service, spec, true).await else {
+            return Ok(Restarted::Started);
+        };
+
+        self.spawn(service, previous, true).await
+            .map_err(|rollback| io::Error::other(format!("{}, and it didn't come back with its previous args: {}", error, rollback)))?;
+        Ok(Restarted::RolledBack(error))
+    }
+
+    /**
+     * whether the child is running, false while it is being (re)started
+     */
+    pub async fn is_running(&self, service: Service) -> bool {
+        match self.children.lock().await.get_mut(&service) {
+            Some(child) => matches!(child.process.try_wait(), Ok(None)),
+            None => false
+        }
+    }
+
+    async fn spawn(&self, service: Service, spec: ChildSpec, append_log: bool) -> io::Result<()> {
+        let output = if append_log {
+            OpenOptions::new().create(true).append(true).open(&spec.log_path)?
+        } else {
+            File::create(&spec.log_path)?
+        };
+
+        // in its own process group, so a Ctrl-C only reaches Keiko, which stops its children in order.
+        // Killed when dropped, which happens when Keiko gives up on it before it is ready
+        let mut process = Command::new(&spec.program)
+            .args(&spec.args)
+            .stdout(Stdio::from(output))
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let ready = tokio::time::timeout(STARTUP_TIMEOUT, async {
+            while TcpStream::connect(spec.addr).await.is_err() {
+                if let Some(status) = process.try_wait()? {
+                    return Err(io::Error::other(
+                        format!("{} exited before it was ready: {}", spec.program, status)
+                    ));
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(())
+        }).await;
+
+        match ready {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                let _ = process.kill().await;
+                return Err(error);
+            }
+            Err(_) => {
+                process.kill().await?;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} didn't listen on {} within {:?}", spec.program, spec.addr, STARTUP_TIMEOUT),
+                ));
+            }
+        }
+
+        self.children.lock().await.insert(service, RunningChild { spec, process });
+        Ok(())
+    }
+
+    async fn terminate(&self, service: Service) -> io::Result<Option<ChildSpec>> {
+        let Some(mut child) = self.children.lock().await.remove(&service) else {
+            return Ok(None);
+        };
+
+        stop_process(&child.spec.program, &mut child.process).await?;
+        Ok(Some(child.spec))
+    }
+}
+
+/**
+ * sends SIGTERM and waits for the process to exit, kills it after STOP_TIMEOUT
+ */
+async fn stop_process(program: &str, process: &mut Child) -> io::Result<ExitStatus> {
+    if let Some(pid) = process.id() {
+        match kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+            // already exited
+            Ok(()) | Err(Errno::ESRCH) => {}
+            Err(error) => return Err(error.into())
+        }
+    }
+
+    // give it time to flush its database, MDBX and sqlite don't like being killed mid-write
+    match tokio::time::timeout(STOP_TIMEOUT, process.wait()).await {
+        Ok(status) => status,
+        Err(_) => {
+            println!("{} didn't exit within {:?}, killing it", program, STOP_TIMEOUT);
+            process.kill().await?;
+            process.wait().await
+        }
+    }
+}
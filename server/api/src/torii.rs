@@ -0,0 +1,251 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::abi::Abi;
+use crate::error::{ApiError, ApiResult};
+use crate::manifest::{Manifest, ManifestModel};
+use crate::model_storage::ModelLayout;
+use crate::server_state::ServerState;
+
+/// Entities per page when the query doesn't say.
+const DEFAULT_PAGE_SIZE: u32 = 25;
+
+/// Torii caps pages itself, but a page this size is already too big to render.
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Deserialize)]
+pub struct EntityQuery {
+    /// full, short or struct name, `dojo_examples::models::position`, `position` or `Position`
+    pub model: String,
+    /// only the entities with these key values, by member name
+    #[serde(default)]
+    pub keys: Map<String, Value>,
+    pub first: Option<u32>,
+    /// the `next_cursor` of the previous page
+    pub after: Option<String>,
+}
+
+/// A page of entities, flattened into table rows: nested structs are columns like `vec.x`.
+#[derive(Serialize)]
+pub struct EntityPage {
+    pub model: String,
+    pub total_count: u64,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+    /// None on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub key: bool,
+}
+
+#[derive(Serialize)]
+pub struct Row {
+    pub entity_id: Option<String>,
+    pub cursor: Option<String>,
+    /// by column name, in the same shape the storage and contract APIs use
+    pub values: Map<String, Value>,
+}
+
+/// A model's members with the ABI that describes their types.
+struct ModelSchema {
+    name: String,
+    struct_name: String,
+    /// (member, type, key)
+    members: Vec<(String, String, bool)>,
+    abi: Abi,
+}
+
+impl ModelSchema {
+    fn load(state: &ServerState, model: &str) -> ApiResult<Self> {
+        let manifest = Manifest::load(&state.manifest_base_dir)
+            .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+        let model: &ManifestModel = manifest.find_model(model)
+            .ok_or_else(|| ApiError::not_found(format!("no model `{}` in the manifest", model)))?;
+
+        let abi = manifest.model_abi(model)?;
+        let layout = ModelLayout::new(model, abi.clone())
+            .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+        Ok(Self {
+            name: model.name.clone(),
+            struct_name: layout.struct_name,
+            members: layout.members.into_iter().map(|member| (member.name, member.ty, member.key)).collect(),
+            abi,
+        })
+    }
+
+    /// the GraphQL selection of a type, structs are selected member by member
+    fn selection(&self, ty: &str) -> String {
+        match self.abi.find_struct(ty) {
+            Some(abi_struct) => {
+                let members: Vec<String> = abi_struct.members.iter()
+                    .map(|member| format!("{}{}", member.name, self.selection(&member.r#type)))
+                    .collect();
+                format!(" {{ {} }}", members.join(" "))
+            }
+            None => String::new(),
+        }
+    }
+
+    /// the leaves of a type, `vec` of a `Vec2` is `vec.x` and `vec.y`
+    fn columns(&self, prefix: &str, ty: &str, key: bool, columns: &mut Vec<Column>) {
+        match self.abi.find_struct(ty) {
+            Some(abi_struct) => {
+                for member in &abi_struct.members {
+                    self.columns(&format!("{}.{}", prefix, member.name), &member.r#type, key, columns);
+                }
+            }
+            None => columns.push(Column { name: prefix.to_string(), ty: ty.to_string(), key }),
+        }
+    }
+}
+
+/**
+ * flattens nested objects into dotted names, the way the columns are named
+ */
+pub fn flatten(prefix: &str, value: Value, values: &mut Map<String, Value>) {
+    match value {
+        Value::Object(members) => {
+            for (name, value) in members {
+                flatten(&format!("{}.{}", prefix, name), value, values);
+            }
+        }
+        value => {
+            values.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+/**
+ * the reverse of `flatten`, `vec.x` becomes `{ "vec": { "x": .. } }`
+ */
+pub fn unflatten(values: &Map<String, Value>) -> Map<String, Value> {
+    let mut nested = Map::new();
+
+    for (name, value) in values {
+        let mut path: Vec<&str> = name.split('.').collect();
+        let last = path.pop().unwrap_or_default();
+
+        let mut object = &mut nested;
+        for part in path {
+            object = match object.entry(part).or_insert_with(|| Value::Object(Map::new())) {
+                Value::Object(inner) => inner,
+                _ => unreachable!("a member is either a struct or a leaf"),
+            };
+        }
+        object.insert(last.to_string(), value.clone());
+    }
+
+    nested
+}
+
+/// Torii names a model's query after the struct, in camel case.
+fn query_name(struct_name: &str) -> String {
+    let mut chars = struct_name.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}Models", first.to_lowercase(), chars.as_str()),
+        None => String::new(),
+    }
+}
+
+/**
+ * runs a query against Torii's GraphQL endpoint, GraphQL errors are the request's fault
+ */
+pub async fn graphql(state: &ServerState, query: &str) -> ApiResult<Value> {
+    let url = state.torii_url.join("graphql")?;
+
+    let response: Value = reqwest::Client::new()
+        .post(url)
+        .json(&json!({ "query": query }))
+        .send().await
+        .and_then(|response| response.error_for_status())
+        .map_err(|error| ApiError::new(StatusCode::BAD_GATEWAY, format!("Torii didn't answer: {}", error)))?
+        .json().await
+        .map_err(|error| ApiError::new(StatusCode::BAD_GATEWAY, format!("Torii answered with invalid JSON: {}", error)))?;
+
+    if let Some(Value::Array(errors)) = response.get("errors") {
+        let messages: Vec<&str> = errors.iter().filter_map(|error| error["message"].as_str()).collect();
+        return Err(ApiError::bad_request(format!("Torii rejected the query: {}", messages.join(", "))));
+    }
+
+    Ok(response["data"].clone())
+}
+
+/**
+ * a page of a model's entities as Torii indexed them
+ */
+pub async fn entities(state: &ServerState, query: &EntityQuery) -> ApiResult<EntityPage> {
+    let schema = ModelSchema::load(state, &query.model)?;
+
+    let mut filters = vec![];
+    for (name, value) in &query.keys {
+        let Some((_, ty, _)) = schema.members.iter().find(|(member, _, key)| member == name && *key) else {
+            return Err(ApiError::bad_request(format!("`{}` is not a key of {}", name, schema.name)));
+        };
+        // in Torii's own formatting, `"0x2a"` and `42` find the same entity
+        let value = schema.abi.torii_scalar(ty, value)
+            .map_err(|error| ApiError::bad_request(format!("can't filter on `{}`: {:#}", name, error)))?;
+        // JSON strings, numbers and booleans are valid GraphQL literals
+        filters.push(format!("{}EQ: {}", name, value));
+    }
+
+    let mut arguments = vec![format!("first: {}", query.first.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))];
+    if let Some(after) = &query.after {
+        arguments.push(format!("after: {}", Value::String(after.clone())));
+    }
+    if !filters.is_empty() {
+        arguments.push(format!("where: {{ {} }}", filters.join(", ")));
+    }
+
+    let selection: Vec<String> = schema.members.iter()
+        .map(|(name, ty, _)| format!("{}{}", name, schema.selection(ty)))
+        .collect();
+
+    let name = query_name(&schema.struct_name);
+    let data = graphql(state, &format!(
+        "{{ {}({}) {{ totalCount pageInfo {{ hasNextPage endCursor }} edges {{ cursor node {{ {} entity {{ id }} }} }} }} }}",
+        name,
+        arguments.join(", "),
+        selection.join(" "),
+    )).await?;
+    let models = &data[&name];
+
+    let mut columns = vec![];
+    for (name, ty, key) in &schema.members {
+        schema.columns(name, ty, *key, &mut columns);
+    }
+
+    let mut rows = vec![];
+    for edge in models["edges"].as_array().into_iter().flatten() {
+        let node = &edge["node"];
+        let mut values = Map::new();
+
+        for (name, ty, _) in &schema.members {
+            // Torii's own representation, when it can't be read as the member's type
+            let value = schema.abi.normalize(ty, &node[name]).unwrap_or_else(|_| node[name].clone());
+            flatten(name, value, &mut values);
+        }
+
+        rows.push(Row {
+            entity_id: node["entity"]["id"].as_str().map(str::to_string),
+            cursor: edge["cursor"].as_str().map(str::to_string),
+            values,
+        });
+    }
+
+    let has_next_page = models["pageInfo"]["hasNextPage"].as_bool().unwrap_or(false);
+
+    Ok(EntityPage {
+        model: schema.name,
+        total_count: models["totalCount"].as_u64().unwrap_or_default(),
+        columns,
+        rows,
+        next_cursor: has_next_page.then(|| models["pageInfo"]["endCursor"].as_str().map(str::to_string)).flatten(),
+    })
+}
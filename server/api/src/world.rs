@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::Serialize;
+use serde_json::Value;
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BlockId, BlockTag, ContractClass, FieldElement, FunctionCall};
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+use starknet::providers::Provider;
+use crate::abi::Abi;
+use crate::auth::{self, Grantee};
+use crate::chain::{self, StarknetProvider};
+use crate::error::{ApiError, ApiResult};
+use crate::indexing;
+use crate::manifest::{self, Manifest, ManifestContract};
+use crate::model_storage::{LayoutMember, ModelLayout};
+use crate::server_state::ServerState;
+
+/// Getters of the world that take no arguments, or the world's resource id, asked when the ABI has them.
+const WORLD_GETTERS: [&str; 5] = ["metadata", "metadata_uri", "executor", "initializer", "base"];
+
+/// How a resource in the manifest compares to the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// the class hash matches the manifest
+    Deployed,
+    /// deployed with another class hash than the manifest's, the migration is behind
+    Outdated,
+    Undeployed,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct WorldReport {
+    #[serde_as(as = "UfeHex")]
+    pub world_address: FieldElement,
+    #[serde(flatten)]
+    pub world: ContractInfo,
+    /// the world's getters, by name, decoded with the world's ABI
+    pub info: BTreeMap<String, Value>,
+    pub models: Vec<ModelInfo>,
+    pub contracts: Vec<ContractInfo>,
+    /// why the owners and writers are missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_error: Option<String>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct ContractInfo {
+    pub name: String,
+    #[serde_as(as = "Option<UfeHex>")]
+    pub address: Option<FieldElement>,
+    #[serde_as(as = "UfeHex")]
+    pub manifest_class_hash: FieldElement,
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class_hash: Option<FieldElement>,
+    pub status: Status,
+    pub owners: Vec<Grantee>,
+    /// the models it is a writer of
+    pub writes: Vec<String>,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub namespace: String,
+    #[serde_as(as = "UfeHex")]
+    pub manifest_class_hash: FieldElement,
+    /// None when the world's ABI has no `model` getter
+    #[serde_as(as = "Option<UfeHex>")]
+    pub class_hash: Option<FieldElement>,
+    /// models are contracts since Dojo 0.7
+    #[serde_as(as = "Option<UfeHex>")]
+    pub contract_address: Option<FieldElement>,
+    pub status: Option<Status>,
+    /// how many entities Torii indexed, None without a local Torii or before it indexed anything
+    pub entities: Option<u64>,
+    pub layout: Vec<LayoutMember>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout_error: Option<String>,
+}
+
+fn status(manifest_class_hash: FieldElement, class_hash: Option<FieldElement>) -> Status {
+    match class_hash {
+        None => Status::Undeployed,
+        Some(class_hash) if class_hash == FieldElement::ZERO => Status::Undeployed,
+        Some(class_hash) if class_hash == manifest_class_hash => Status::Deployed,
+        Some(_) => Status::Outdated,
+    }
+}
+
+async fn class_hash_at(provider: &StarknetProvider, address: Option<FieldElement>) -> Option<FieldElement> {
+    provider.get_class_hash_at(BlockId::Tag(BlockTag::Pending), address?).await.ok()
+}
+
+/// The world's ABI from the manifest, or from its class when the manifest doesn't have it.
+pub(crate) async fn world_abi(provider: &StarknetProvider, manifest: &Manifest, world_address: FieldElement) -> Option<Abi> {
+    if let Ok(abi) = manifest.abi(&manifest.world) {
+        return Some(abi);
+    }
+
+    match provider.get_class_at(BlockId::Tag(BlockTag::Pending), world_address).await.ok()? {
+        ContractClass::Sierra(class) => Abi::parse(serde_json::from_str(&class.abi).ok()?).ok(),
+        ContractClass::Legacy(_) => None,
+    }
+}
+
+async fn call_world(provider: &StarknetProvider, world_address: FieldElement, function: &str, calldata: Vec<FieldElement>) -> anyhow::Result<Vec<FieldElement>> {
+    Ok(provider.call(
+        FunctionCall {
+            contract_address: world_address,
+            entry_point_selector: get_selector_from_name(function)?,
+            calldata,
+        },
+        BlockId::Tag(BlockTag::Pending),
+    ).await?)
+}
+
+async fn contract_info(
+    provider: &StarknetProvider,
+    contract: &ManifestContract,
+    owners: Vec<Grantee>,
+    writes: Vec<String>,
+) -> ContractInfo {
+    let class_hash = class_hash_at(provider, contract.address).await;
+
+    ContractInfo {
+        name: contract.name.clone(),
+        address: contract.address,
+        manifest_class_hash: contract.class_hash,
+        class_hash,
+        status: status(contract.class_hash, class_hash),
+        owners,
+        writes,
+    }
+}
+
+/**
+ * what the world has registered and deployed, compared to the manifest
+ */
+pub async fn inspect(state: &ServerState) -> ApiResult<WorldReport> {
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let world_address = manifest.world.address
+        .map_or_else(|| FieldElement::from_hex_be(&state.world_address), Ok)?;
+
+    let provider = chain::provider(state);
+    let abi = world_abi(&provider, &manifest, world_address).await;
+
+    let mut info = BTreeMap::new();
+    if let Some(abi) = &abi {
+        for function in WORLD_GETTERS.iter().filter_map(|name| abi.function(name)) {
+            // the ones that take a resource get the world's, which is 0
+            let calldata = vec![FieldElement::ZERO; function.inputs.len().min(1)];
+            let value = match call_world(&provider, world_address, &function.name, calldata).await {
+                Ok(raw) => abi.decode_outputs(function, &raw).unwrap_or_else(|error| Value::String(format!("{:#}", error))),
+                Err(error) => Value::String(format!("{:#}", error)),
+            };
+            info.insert(function.name.clone(), value);
+        }
+    }
+
+    let entity_counts = match &state.torii_db_path {
+        Some(torii_db_path) => indexing::entity_counts(torii_db_path).await.ok(),
+        None => None,
+    };
+    let model_getter = abi.as_ref().and_then(|abi| abi.function("model"));
+
+    let mut models = vec![];
+    for model in &manifest.models {
+        let layout = manifest.model_abi(model).and_then(|abi| ModelLayout::new(model, abi));
+        // the selector and Torii's tables are named after the struct in the model's ABI
+        let struct_name = layout.as_ref().ok().map(|layout| layout.struct_name.clone());
+
+        // before Dojo 0.6 models were registered by name, since then by selector
+        let (class_hash, contract_address) = match (model_getter, &struct_name) {
+            (Some(getter), Some(struct_name)) => {
+                let id = match getter.inputs.first() {
+                    Some(input) if input.name == "name" => cairo_short_string_to_felt(struct_name)?,
+                    _ => get_selector_from_name(struct_name)?,
+                };
+                let raw = call_world(&provider, world_address, "model", vec![id]).await.unwrap_or_default();
+                (raw.first().copied(), raw.get(1).copied())
+            }
+            _ => (None, None),
+        };
+
+        let (layout, layout_error) = match layout {
+            Ok(layout) => (layout.members, None),
+            Err(error) => (vec![], Some(format!("{:#}", error))),
+        };
+
+        models.push(ModelInfo {
+            name: model.name.clone(),
+            namespace: manifest::namespace(&model.name).to_string(),
+            manifest_class_hash: model.class_hash,
+            class_hash,
+            contract_address,
+            status: model_getter.map(|_| status(model.class_hash, class_hash)),
+            entities: entity_counts.as_ref().zip(struct_name.as_ref())
+                .map(|(counts, struct_name)| counts.get(struct_name).copied().unwrap_or_default()),
+            layout,
+            layout_error,
+        });
+    }
+
+    let resources: Vec<FieldElement> = manifest.contracts.iter()
+        .map(|contract| contract.address.unwrap_or_default())
+        .collect();
+
+    // a broken `[auth]` section leaves the permissions out, not the rest of the report
+    let (world_owners, mut writes, owners, auth_error) = match auth::report_with_owners(state, &resources).await {
+        Ok((permissions, owners)) => {
+            // the models each contract writes, from the permissions of every model
+            let mut writes: HashMap<FieldElement, Vec<String>> = HashMap::new();
+            for model in permissions.namespaces.iter().flat_map(|namespace| &namespace.models) {
+                for writer in &model.writers {
+                    writes.entry(writer.address).or_default().push(model.model.clone());
+                }
+            }
+            (permissions.world_owners, writes, owners, None)
+        }
+        Err(error) => (vec![], HashMap::new(), vec![vec![]; resources.len()], Some(error.message().to_string())),
+    };
+
+    let mut contracts = vec![];
+    for (contract, owners) in manifest.contracts.iter().zip(owners) {
+        // an undeployed contract was asked about the world's resource
+        let owners = if contract.address.is_some() { owners } else { vec![] };
+        let writes = contract.address.and_then(|address| writes.remove(&address)).unwrap_or_default();
+        contracts.push(contract_info(&provider, contract, owners, writes).await);
+    }
+
+    Ok(WorldReport {
+        world_address,
+        world: contract_info(&provider, &manifest.world, world_owners, vec![]).await,
+        info,
+        models,
+        contracts,
+        auth_error,
+    })
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::ApiResult;
+use crate::handlers::storage::model;
+use crate::manifest::Manifest;
+use crate::server_state::ServerState;
+use crate::torii;
+use crate::world_export::{self, ExportedEntity, ModelExport, Source, WorldExport};
+
+#[derive(Deserialize)]
+pub struct DiffRequest {
+    /// a file of the export endpoint
+    pub before: WorldExport,
+    /// the live chain when there is none
+    pub after: Option<WorldExport>,
+}
+
+#[derive(Serialize)]
+pub struct WorldDiff {
+    pub changed: bool,
+    /// only the models that changed
+    pub models: Vec<ModelDiff>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ModelDiff {
+    pub model: String,
+    pub created: Vec<ExportedEntity>,
+    pub deleted: Vec<ExportedEntity>,
+    pub changed: Vec<ChangedEntity>,
+    pub unchanged: usize,
+}
+
+#[derive(Serialize)]
+pub struct ChangedEntity {
+    pub keys: Map<String, Value>,
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Serialize)]
+pub struct FieldChange {
+    /// the column name, `vec.x` for nested members
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl ModelDiff {
+    fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.deleted.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn flattened(values: &Map<String, Value>) -> Map<String, Value> {
+    let mut flat = Map::new();
+    for (name, value) in values {
+        torii::flatten(name, value.clone(), &mut flat);
+    }
+    flat
+}
+
+/// A model's entities by their keys, the keys are compared as written in the files.
+fn by_keys(model: Option<&ModelExport>) -> BTreeMap<String, &ExportedEntity> {
+    model.into_iter()
+        .flat_map(|model| &model.entities)
+        .map(|entity| (Value::Object(entity.keys.clone()).to_string(), entity))
+        .collect()
+}
+
+fn diff_model(name: &str, before: Option<&ModelExport>, after: Option<&ModelExport>) -> ModelDiff {
+    let mut diff = ModelDiff { model: name.to_string(), ..Default::default() };
+    let before = by_keys(before);
+    let mut after = by_keys(after);
+
+    for (keys, old) in before {
+        let Some(new) = after.remove(&keys) else {
+            diff.deleted.push(ExportedEntity { keys: old.keys.clone(), values: old.values.clone() });
+            continue;
+        };
+
+        let (old_values, new_values) = (flattened(&old.values), flattened(&new.values));
+        let mut fields: Vec<FieldChange> = new_values.iter()
+            .filter(|(field, value)| old_values.get(*field) != Some(value))
+            .map(|(field, value)| FieldChange {
+                field: field.clone(),
+                before: old_values.get(field).cloned().unwrap_or(Value::Null),
+                after: value.clone(),
+            })
+            .collect();
+        // members the model no longer has
+        fields.extend(old_values.iter()
+            .filter(|(field, _)| !new_values.contains_key(*field))
+            .map(|(field, value)| FieldChange { field: field.clone(), before: value.clone(), after: Value::Null }));
+
+        if fields.is_empty() {
+            diff.unchanged += 1;
+        } else {
+            diff.changed.push(ChangedEntity { keys: old.keys.clone(), fields });
+        }
+    }
+
+    for new in after.into_values() {
+        diff.created.push(ExportedEntity { keys: new.keys.clone(), values: new.values.clone() });
+    }
+
+    diff
+}
+
+/**
+ * the entities created, deleted and changed between two exports, models are matched by struct name
+ */
+pub fn diff(before: &WorldExport, after: &WorldExport) -> WorldDiff {
+    let mut names: Vec<&str> = before.models.iter().map(|model| model.struct_name.as_str()).collect();
+    for model in &after.models {
+        if !names.contains(&model.struct_name.as_str()) {
+            names.push(&model.struct_name);
+        }
+    }
+
+    let models: Vec<ModelDiff> = names.into_iter()
+        .map(|struct_name| {
+            let old = before.models.iter().find(|model| model.struct_name == struct_name);
+            let new = after.models.iter().find(|model| model.struct_name == struct_name);
+            let name = new.or(old).map_or(struct_name, |model| model.name.as_str());
+            diff_model(name, old, new)
+        })
+        .filter(|model| !model.is_empty())
+        .collect();
+
+    WorldDiff { changed: !models.is_empty(), models }
+}
+
+/// Rewrites the keys and values of the models the manifest has through their layout, the others are compared as written.
+fn normalize(state: &ServerState, manifest: &Manifest, export: &mut WorldExport) {
+    for exported in &mut export.models {
+        let Some(manifest_model) = manifest.find_model(&exported.struct_name).or_else(|| manifest.find_model(&exported.name)) else {
+            continue;
+        };
+        let Ok((_, layout)) = model::layout(state, &manifest_model.name) else {
+            continue;
+        };
+
+        for entity in &mut exported.entities {
+            entity.keys = layout.normalize(&entity.keys);
+            entity.values = layout.normalize(&entity.values);
+        }
+    }
+}
+
+/**
+ * diffs a file of the export endpoint against another one, or against what the world stores now.
+ * Values are compared once normalized through the model's layout, `"0x2a"` and `42` are the same u32
+ */
+pub async fn diff_with(state: &ServerState, request: DiffRequest) -> ApiResult<WorldDiff> {
+    let DiffRequest { mut before, after } = request;
+    let mut after = match after {
+        Some(after) => after,
+        None => world_export::export(state, Source::Chain).await?,
+    };
+
+    // without a manifest there is no layout, the files are compared as written
+    if let Ok(manifest) = Manifest::load(&state.manifest_base_dir) {
+        normalize(state, &manifest, &mut before);
+        normalize(state, &manifest, &mut after);
+    }
+
+    Ok(diff(&before, &after))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::FieldElement;
+    use super::*;
+
+    fn entity(keys: Value, values: Value) -> ExportedEntity {
+        serde_json::from_value(json!({ "keys": keys, "values": values })).unwrap()
+    }
+
+    fn export(models: Vec<(&str, Vec<ExportedEntity>)>) -> WorldExport {
+        WorldExport {
+            version: 1,
+            world_address: FieldElement::ONE,
+            dojo_version: "0.7.0".to_string(),
+            models: models.into_iter()
+                .map(|(struct_name, entities)| ModelExport {
+                    name: format!("dojo_examples::models::{}", struct_name.to_lowercase()),
+                    struct_name: struct_name.to_string(),
+                    members: vec![],
+                    entities,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn same_exports_have_no_diff() {
+        let models = || vec![("Position", vec![entity(json!({ "player": "0x1" }), json!({ "vec": { "x": 1, "y": 2 } }))])];
+
+        let diff = diff(&export(models()), &export(models()));
+        assert!(!diff.changed);
+        assert!(diff.models.is_empty());
+    }
+
+    #[test]
+    fn lists_created_deleted_and_changed_entities() {
+        let before = export(vec![("Position", vec![
+            entity(json!({ "player": "0x1" }), json!({ "vec": { "x": 1, "y": 2 } })),
+            entity(json!({ "player": "0x2" }), json!({ "vec": { "x": 0, "y": 0 } })),
+            entity(json!({ "player": "0x3" }), json!({ "vec": { "x": 5, "y": 5 } })),
+        ])]);
+        let after = export(vec![("Position", vec![
+            entity(json!({ "player": "0x1" }), json!({ "vec": { "x": 1, "y": 3 } })),
+            entity(json!({ "player": "0x3" }), json!({ "vec": { "x": 5, "y": 5 } })),
+            entity(json!({ "player": "0x4" }), json!({ "vec": { "x": 9, "y": 9 } })),
+        ])]);
+
+        let diff = diff(&before, &after);
+        assert!(diff.changed);
+        let [model] = diff.models.as_slice() else { panic!("one model changed") };
+
+        assert_eq!(model.model, "dojo_examples::models::position");
+        assert_eq!(model.unchanged, 1);
+        assert_eq!(model.deleted.iter().map(|entity| entity.keys["player"].clone()).collect::<Vec<_>>(), [json!("0x2")]);
+        assert_eq!(model.created.iter().map(|entity| entity.keys["player"].clone()).collect::<Vec<_>>(), [json!("0x4")]);
+
+        let [changed] = model.changed.as_slice() else { panic!("one entity changed") };
+        assert_eq!(changed.keys["player"], json!("0x1"));
+        let fields: Vec<_> = changed.fields.iter().map(|field| (field.field.as_str(), &field.before, &field.after)).collect();
+        assert_eq!(fields, [("vec.y", &json!(2), &json!(3))]);
+    }
+
+    #[test]
+    fn reports_members_and_models_that_are_gone() {
+        let before = export(vec![
+            ("Moves", vec![entity(json!({ "player": "0x1" }), json!({ "remaining": 3, "can_move": true }))]),
+            ("Legacy", vec![entity(json!({ "id": 1 }), json!({ "value": 1 }))]),
+        ]);
+        let after = export(vec![
+            ("Moves", vec![entity(json!({ "player": "0x1" }), json!({ "remaining": 3 }))]),
+        ]);
+
+        let diff = diff(&before, &after);
+        let moves = diff.models.iter().find(|model| model.model.ends_with("moves")).unwrap();
+        let fields: Vec<_> = moves.changed[0].fields.iter().map(|field| (field.field.as_str(), &field.before, &field.after)).collect();
+        assert_eq!(fields, [("can_move", &json!(true), &Value::Null)]);
+
+        let legacy = diff.models.iter().find(|model| model.model.ends_with("legacy")).unwrap();
+        assert_eq!(legacy.deleted.len(), 1);
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_with::serde_as;
+use starknet::accounts::{Account, Call, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::FieldElement;
+use starknet::core::types::contract::AbiFunction;
+use starknet::core::utils::get_selector_from_name;
+use crate::abi::Abi;
+use crate::chain;
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::storage::model;
+use crate::manifest::Manifest;
+use crate::model_storage::ModelLayout;
+use crate::server_state::ServerState;
+use crate::torii::{self, EntityQuery};
+use crate::world;
+
+/// Bumped when the file's shape changes, older files are still read.
+const EXPORT_VERSION: u32 = 1;
+
+/// Entities asked from Torii at once while listing a model's entities.
+const EXPORT_PAGE_SIZE: u32 = 1000;
+
+/// Entities written in one transaction while importing.
+const IMPORT_BATCH_SIZE: usize = 50;
+
+/// Every entity of every model, by member name, independent of how a Dojo version stores them.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub struct WorldExport {
+    pub version: u32,
+    #[serde_as(as = "UfeHex")]
+    pub world_address: FieldElement,
+    pub dojo_version: String,
+    pub models: Vec<ModelExport>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelExport {
+    pub name: String,
+    pub struct_name: String,
+    pub members: Vec<ExportMember>,
+    pub entities: Vec<ExportedEntity>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub key: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedEntity {
+    pub keys: Map<String, Value>,
+    pub values: Map<String, Value>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Torii lists the entities, their values are read from the world's storage
+    #[default]
+    Chain,
+    /// the values as Torii indexed them, without reading the world
+    Torii,
+}
+
+#[derive(Serialize)]
+pub struct Imported {
+    pub models: Vec<ModelImport>,
+    /// in the file, but not in the world's manifest
+    pub skipped_models: Vec<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ModelImport {
+    pub model: String,
+    pub entities: usize,
+    /// members that are in the file, but no longer in the model
+    pub skipped_members: Vec<String>,
+    pub failed: Vec<FailedEntity>,
+}
+
+#[derive(Serialize)]
+pub struct FailedEntity {
+    pub keys: Map<String, Value>,
+    pub error: String,
+}
+
+async fn export_model(state: &ServerState, name: &str, layout: ModelLayout, source: Source) -> ApiResult<ModelExport> {
+    let world_address = model::world_address(state)?;
+    let key_names: BTreeSet<&str> = layout.members.iter()
+        .filter(|member| member.key)
+        .map(|member| member.name.as_str())
+        .collect();
+
+    let mut entities = vec![];
+    let mut after = None;
+    loop {
+        let page = torii::entities(state, &EntityQuery {
+            model: name.to_string(),
+            keys: Map::new(),
+            first: Some(EXPORT_PAGE_SIZE),
+            after: after.take(),
+        }).await?;
+
+        for row in &page.rows {
+            let (keys, torii_values): (Map<String, Value>, Map<String, Value>) = row.values.clone().into_iter()
+                .partition(|(name, _)| key_names.contains(name.split('.').next().unwrap_or_default()));
+
+            let values = match source {
+                Source::Torii => torii::unflatten(&torii_values),
+                Source::Chain => {
+                    let storage = layout.entity(&Value::Object(torii::unflatten(&keys)))
+                        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+                    let raw = model::read(state, world_address, &storage).await?;
+                    match layout.decode(&raw)? {
+                        Value::Object(values) => values,
+                        _ => Map::new(),
+                    }
+                }
+            };
+
+            entities.push(ExportedEntity { keys: torii::unflatten(&keys), values });
+        }
+
+        match page.next_cursor {
+            Some(cursor) if !page.rows.is_empty() => after = Some(cursor),
+            _ => break,
+        }
+    }
+
+    Ok(ModelExport {
+        name: name.to_string(),
+        struct_name: layout.struct_name.clone(),
+        members: layout.members.into_iter()
+            .map(|member| ExportMember { name: member.name, ty: member.ty, key: member.key })
+            .collect(),
+        entities,
+    })
+}
+
+/**
+ * every entity of every model in the manifest, listed by Torii
+ */
+pub async fn export(state: &ServerState, source: Source) -> ApiResult<WorldExport> {
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+
+    let mut models = vec![];
+    for manifest_model in &manifest.models {
+        let (name, layout) = model::layout(state, &manifest_model.name)?;
+        models.push(export_model(state, &name, layout, source).await?);
+    }
+
+    Ok(WorldExport {
+        version: EXPORT_VERSION,
+        world_address: model::world_address(state)?,
+        dojo_version: state.config.node_config().versions.dojo,
+        models,
+    })
+}
+
+/**
+ * writes every entity of the file through the world's `set_entity`, matching models by struct name
+ * and members by name. The world emits its usual events, so Torii indexes the imported entities.
+ * The first dev account sends the transactions, it owns the world after a migration from Keiko.
+ */
+pub async fn import(state: &ServerState, export: &WorldExport) -> ApiResult<Imported> {
+    if export.version > EXPORT_VERSION {
+        return Err(ApiError::bad_request(format!(
+            "the file is version {}, this Keiko reads up to version {}", export.version, EXPORT_VERSION
+        )));
+    }
+
+    let manifest = Manifest::load(&state.manifest_base_dir)
+        .map_err(|error| ApiError::not_found(format!("{:#}", error)))?;
+    let world_address = model::world_address(state)?;
+
+    let signer = chain::dev_signer(state, None).await?;
+    let world_abi = world::world_abi(signer.provider(), &manifest, world_address).await
+        .ok_or_else(|| ApiError::not_found("the world's ABI is neither in the manifest nor on chain"))?;
+    let set_entity = world_abi.function("set_entity")
+        .ok_or_else(|| ApiError::conflict("the world has no `set_entity`, its entities can't be imported"))?;
+    let selector = get_selector_from_name(&set_entity.name)?;
+
+    let mut models = vec![];
+    let mut skipped_models = vec![];
+
+    for exported in &export.models {
+        let Some(manifest_model) = manifest.find_model(&exported.struct_name).or_else(|| manifest.find_model(&exported.name)) else {
+            skipped_models.push(exported.name.clone());
+            continue;
+        };
+        let (name, layout) = model::layout(state, &manifest_model.name)?;
+
+        let writable: BTreeSet<&str> = layout.members.iter()
+            .filter(|member| !member.key)
+            .map(|member| member.name.as_str())
+            .collect();
+
+        let mut imported = ModelImport {
+            model: name.clone(),
+            skipped_members: exported.members.iter()
+                .filter(|member| !member.key && !writable.contains(member.name.as_str()))
+                .map(|member| member.name.clone())
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut pending = vec![];
+        for entity in &exported.entities {
+            let values: Map<String, Value> = entity.values.iter()
+                .filter(|(member, _)| writable.contains(member.as_str()))
+                .map(|(member, value)| (member.clone(), value.clone()))
+                .collect();
+
+            match set_entity_call(state, &world_abi, set_entity, &layout, world_address, &entity.keys, &values).await {
+                Ok(calldata) => pending.push((entity, Call { to: world_address, selector, calldata })),
+                Err(error) => imported.failed.push(FailedEntity {
+                    keys: entity.keys.clone(),
+                    error: error.message().to_string(),
+                }),
+            }
+        }
+
+        for batch in pending.chunks(IMPORT_BATCH_SIZE) {
+            let calls = batch.iter().map(|(_, call)| call.clone()).collect();
+            let sent = match signer.execute(calls).send().await {
+                Ok(result) => chain::wait_for_receipt(signer.provider(), result.transaction_hash).await
+                    .map(|_| ())
+                    .map_err(|error| format!("{:#}", error)),
+                Err(error) => Err(format!("transaction failed: {}", error)),
+            };
+
+            match sent {
+                Ok(()) => imported.entities += batch.len(),
+                Err(error) => imported.failed.extend(batch.iter().map(|(entity, _)| FailedEntity {
+                    keys: entity.keys.clone(),
+                    error: error.clone(),
+                })),
+            }
+        }
+
+        models.push(imported);
+    }
+
+    Ok(Imported { models, skipped_models })
+}
+
+/// The calldata of `set_entity` for an entity, the values missing from the file keep what the world has.
+async fn set_entity_call(
+    state: &ServerState,
+    world_abi: &Abi,
+    set_entity: &AbiFunction,
+    layout: &ModelLayout,
+    world_address: FieldElement,
+    keys: &Map<String, Value>,
+    values: &Map<String, Value>,
+) -> ApiResult<Vec<FieldElement>> {
+    let storage = layout.entity(&Value::Object(keys.clone()))
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+    let mut slots = model::read(state, world_address, &storage).await?;
+    layout.encode(&mut slots, values)
+        .map_err(|error| ApiError::bad_request(format!("{:#}", error)))?;
+
+    let (fields, bits) = layout.unpacked(&slots);
+    let felts = |felts: &[FieldElement]| Value::from(felts.iter().map(|felt| format!("{:#x}", felt)).collect::<Vec<_>>());
+
+    let mut args = Map::new();
+    for input in &set_entity.inputs {
+        let arg = match input.name.as_str() {
+            "model" | "model_selector" => Value::from(format!("{:#x}", layout.selector)),
+            "keys" => felts(&storage.keys),
+            "offset" => Value::from(0),
+            "values" => felts(&fields),
+            // a span of bit sizes until Dojo 0.7, the `Layout` enum since
+            "layout" if input.r#type.contains("u8") => Value::from(bits.clone()),
+            "layout" => json!({ "Fixed": bits.clone() }),
+            other => return Err(ApiError::conflict(format!("the world's `set_entity` takes an unknown `{}` argument", other))),
+        };
+        args.insert(input.name.clone(), arg);
+    }
+
+    Ok(world_abi.encode_inputs(set_entity, &Value::Object(args))?)
+}
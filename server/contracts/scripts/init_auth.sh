@@ -1,20 +1,11 @@
 #!/bin/bash
 set -euo pipefail
-pushd $(dirname "$0")/..
 
-source scripts/env.sh
+# Grants the writers the manifest asks for, Position and Moves to the actions, through Keiko.
+# Keiko started with --auto-auth does this after every migration on its own.
+KEIKO_URL=${KEIKO_URL:-"http://localhost:3000"}
 
-# enable system -> component authorizations
-COMPONENTS=("Position" "Moves" )
+curl --fail-with-body -s -X POST "$KEIKO_URL/api/auth/apply"
 
-for component in ${COMPONENTS[@]}; do
-    sleep 0.1
-    sozo auth writer $component $DOJO_EXAMPLES_ACTIONS_ACTIONS
-done
-
-for component in ${COMPONENTS[@]}; do
-    sleep 0.1
-    sozo auth writer $component $DOJO_EXAMPLES_ACTIONS_ACTIONS
-done
-
-echo "Default authorizations have been successfully set."
\ No newline at end of file
+echo
+echo "Default authorizations have been successfully set."
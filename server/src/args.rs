@@ -3,8 +3,17 @@ use clap::{Args, Parser};
 use jsonrpsee_http_client::{HttpClient, HttpClientBuilder};
 use url::Url;
 use std::str::FromStr;
+use keiko_api::auth::AuthConfig;
+use keiko_api::genesis_builder::{self, GenesisConfig};
+use keiko_api::node_config::{ConfigSource, Fork, KatanaUpdate, Mining, NodeConfig, Versions, redact_url};
+use keiko_api::metrics::Metrics;
 use keiko_api::server_state;
+use keiko_api::supervisor::Supervisor;
 use std::net::SocketAddr;
+use std::process;
+use std::sync::{Arc, RwLock};
+use serde::{Serialize, Serializer};
+use crate::{config_file, utils};
 
 const LOCAL_KATANA: &str = "http://0.0.0.0:5050";
 const LOCAL_TORII: &str = "http://0.0.0.0:8080";
@@ -15,14 +24,22 @@ pub const KATANA_LOG: &str = "log/katana.log.json";
 pub const TORII_LOG: &str = "log/torii.log";
 pub const TORII_DB: &str = "torii.sqlite";
 
+pub const KEIKO_VERSION: &str = include_str!("../../VERSION");
+pub const DOJO_VERSION: &str = include_str!("../../DOJO_VERSION");
 
-#[derive(Debug, Clone)]
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Config {
     pub server: ServerOptions,
     pub starknet: StarknetOptions,
     pub katana: KatanaOptions,
     pub torii: ToriiOptions,
-    pub world_address: String,
+    pub slot: SlotOptions,
+    /// not options, the `[auth]` section of the config file
+    pub auth: AuthConfig,
+    /// the `[genesis]` section, the genesis is generated from it when it's there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genesis: Option<GenesisConfig>,
 }
 
 
@@ -33,24 +50,58 @@ impl From<KeikoArgs> for Config {
             starknet: args.starknet,
             katana: args.katana,
             torii: args.torii,
-            world_address: "".to_string(),
+            slot: args.slot,
+            auth: AuthConfig::default(),
+            genesis: None,
         }
     }
 }
 
 impl Config {
     pub fn new() -> Self {
-        let keiko_args = KeikoArgs::parse();
-        let config = Self::from(keiko_args);
+        let args = config_file::layered_args(std::env::args_os()).unwrap_or_else(|errors| exit_with_errors(errors));
+
+        let keiko_args = KeikoArgs::parse_from(args);
+        let print_config = keiko_args.print_config;
+        let auth = config_file::data_section(&keiko_args.config, "auth").unwrap_or_else(|errors| exit_with_errors(errors));
+        let genesis = config_file::data_section(&keiko_args.config, "genesis").unwrap_or_else(|errors| exit_with_errors(errors));
+        let config = Self { auth, genesis, ..Self::from(keiko_args) };
+
+        if print_config {
+            print!("{}", toml::to_string_pretty(&config).expect("able to serialize config"));
+            process::exit(0);
+        }
+
+        if let Err(errors) = config.validate() {
+            exit_with_errors(errors);
+        }
 
         config
     }
 }
 
+fn exit_with_errors(errors: Vec<String>) -> ! {
+    for error in errors {
+        eprintln!("error: {}", error);
+    }
+    process::exit(2);
+}
+
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 pub struct KeikoArgs {
+    #[arg(long)]
+    #[arg(value_name = "PATH")]
+    #[arg(default_value = "keiko.toml")]
+    #[arg(env = "KEIKO_CONFIG")]
+    #[arg(help = "Path to the config file. Env vars and flags take precedence over it.")]
+    pub config: PathBuf,
+
+    #[arg(long)]
+    #[arg(help = "Print the effective configuration and exit.")]
+    pub print_config: bool,
+
     #[command(flatten)]
     #[command(next_help_heading = "Server options")]
     pub server: ServerOptions,
@@ -66,9 +117,13 @@ pub struct KeikoArgs {
     #[command(flatten)]
     #[command(next_help_heading = "Torii options")]
     pub torii: ToriiOptions,
+
+    #[command(flatten)]
+    #[command(next_help_heading = "Slot options")]
+    pub slot: SlotOptions,
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Args, Clone, Serialize)]
 pub struct ServerOptions {
     #[arg(long)]
     #[arg(default_value = "3000")]
@@ -95,10 +150,15 @@ pub struct ServerOptions {
     #[arg(help = "Builds the dashboard if set to true")]
     #[arg(env = "PROD")]
     pub prod: bool,
+
+    #[arg(long)]
+    #[arg(env = "AUTO_AUTH")]
+    #[arg(help = "Grant the writers and owners the manifests and the [auth] section ask for after every migration.")]
+    pub auto_auth: bool,
 }
 
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Args, Clone, Serialize)]
 pub struct StarknetOptions {
     #[arg(long)]
     #[arg(help = "Disable charging fee for transactions.")]
@@ -110,12 +170,37 @@ pub struct StarknetOptions {
     #[arg(env = "DISABLE_VALIDATE")]
     pub disable_validate: bool,
 
+    #[arg(long)]
+    #[arg(env = "SEED")]
+    #[arg(help = "Specify the seed the dev accounts are derived from.")]
+    #[arg(long_help = "Specify the seed the dev accounts are derived from. The same seed always gives the \
+                       same accounts. Setting it, --accounts or --account-balance generates the genesis.")]
+    pub seed: Option<String>,
+
+    #[arg(long)]
+    #[arg(env = "ACCOUNTS")]
+    #[arg(help = "Number of dev accounts to generate.")]
+    pub accounts: Option<u16>,
+
+    #[arg(long)]
+    #[arg(value_name = "WEI")]
+    #[arg(env = "ACCOUNT_BALANCE")]
+    #[arg(help = "The ETH balance of every dev account, in wei.")]
+    pub account_balance: Option<String>,
+
+    #[arg(long)]
+    #[arg(value_name = "ADDRESS")]
+    #[arg(value_delimiter = ',')]
+    #[arg(env = "TOKENS")]
+    #[arg(help = "ERC20 tokens whose balances the account overview shows, besides ETH and STRK.")]
+    pub tokens: Vec<String>,
+
     #[command(flatten)]
     #[command(next_help_heading = "Environment options")]
     pub environment: EnvironmentOptions,
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Args, Clone, Serialize)]
 pub struct EnvironmentOptions {
     #[arg(long)]
     #[arg(help = "The chain ID.")]
@@ -126,6 +211,7 @@ pub struct EnvironmentOptions {
     #[arg(long)]
     #[arg(help = "The gas price.")]
     #[arg(env = "GAS_PRICE")]
+    #[serde(serialize_with = "serialize_u128")]
     pub gas_price: Option<u128>,
 
     #[arg(long)]
@@ -139,7 +225,15 @@ pub struct EnvironmentOptions {
     pub invoke_max_steps: Option<u32>,
 }
 
-#[derive(Debug, Args, Clone)]
+// toml has no integers wider than i64
+fn serialize_u128<S: Serializer>(value: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none()
+    }
+}
+
+#[derive(Debug, Args, Clone, Serialize)]
 pub struct ToriiOptions {
     #[arg(long)]
     #[arg(env = "TORII_SEQUENCER_RPC")]
@@ -152,7 +246,25 @@ pub struct ToriiOptions {
     pub torii_external_url: Option<Url>,
 }
 
-#[derive(Debug, Args, Clone)]
+#[derive(Debug, Args, Clone, Serialize)]
+pub struct SlotOptions {
+    #[arg(long)]
+    #[arg(value_name = "URL")]
+    #[arg(env = "SLOT_KATANA")]
+    #[arg(help = "The url of a deployed (slot) Katana. Keiko won't start a local Katana when set.")]
+    pub slot_katana: Option<Url>,
+
+    #[arg(long)]
+    #[arg(value_name = "URL")]
+    #[arg(env = "SLOT_TORII")]
+    #[arg(help = "The url of a deployed (slot) Torii. Keiko won't start a local Torii when set.")]
+    #[arg(long_help = "The url of a deployed (slot) Torii. Keiko won't start a local Torii when set. \
+                       When only SLOT_KATANA is given, a local Torii is started that indexes the \
+                       remote Katana, or TORII_SEQUENCER_RPC if that is set.")]
+    pub slot_torii: Option<Url>,
+}
+
+#[derive(Debug, Args, Clone, Serialize)]
 pub struct KatanaOptions {
     #[arg(long)]
     #[arg(help = "The world address Keiko uses")]
@@ -194,10 +306,12 @@ pub struct KatanaOptions {
     #[arg(help = "Output logs in JSON format.")]
     pub katana_json_log: bool,
 
-    /// Enable Prometheus metrics.
-    ///
-    /// The metrics will be served at the given interface and port.
-    // #[arg(long, value_name = "SOCKET", value_parser = parse_socket_address, help_heading = "Metrics")]
+    #[arg(long)]
+    #[arg(value_name = "SOCKET")]
+    #[arg(env = "KATANA_METRICS")]
+    #[arg(help = "Enable Prometheus metrics.")]
+    #[arg(long_help = "Enable Prometheus metrics. The metrics will be served at the given interface \
+                       and port, and are included in Keiko's /metrics.")]
     pub katana_metrics: Option<SocketAddr>,
 
     #[arg(long)]
@@ -224,12 +338,51 @@ impl Config {
         format!("storage/{}", self.katana.world_address)
     }
 
+    pub fn get_katana_db_dir(&self) -> PathBuf {
+        match &self.katana.katana_db_dir {
+            Some(katana_db_dir) => katana_db_dir.clone(),
+            None => PathBuf::from(format!("{}/katana-db", self.get_storage_base_dir()))
+        }
+    }
+
+    pub fn get_torii_db_path(&self) -> String {
+        format!("{}/{}", self.get_storage_base_dir(), TORII_DB)
+    }
+
+    pub fn get_genesis_path(&self) -> String {
+        format!("{}/config/genesis.json", self.get_storage_base_dir())
+    }
+
+    /**
+     * how the genesis is generated: the `[genesis]` section with the account options on top,
+     * None when the genesis isn't generated
+     */
+    pub fn genesis_config(&self) -> Option<GenesisConfig> {
+        let starknet = &self.starknet;
+        if self.genesis.is_none() && starknet.seed.is_none() && starknet.accounts.is_none() && starknet.account_balance.is_none() {
+            return None;
+        }
+
+        let mut genesis = self.genesis.clone().unwrap_or_default();
+        if let Some(seed) = &starknet.seed {
+            genesis.seed = seed.clone();
+        }
+        if let Some(accounts) = starknet.accounts {
+            genesis.accounts = accounts;
+        }
+        if let Some(account_balance) = &starknet.account_balance {
+            genesis.eth_balance = account_balance.clone();
+        }
+
+        Some(genesis)
+    }
+
     pub fn get_torii_args(&self) -> Vec<String> {
         let mut args = vec![
             "--world".to_string(),
             self.katana.world_address.clone(),
             "--database".to_string(),
-            format!("{}/{}", self.get_storage_base_dir().clone(), TORII_DB),
+            self.get_torii_db_path(),
         ];
 
         args.push("--allowed-origins".to_string());
@@ -240,7 +393,7 @@ impl Config {
             args.push(torii_external_url.to_string());
         }
 
-        if let Some(sequencer_rpc) = &self.torii.torii_sequencer_rpc {
+        if let Some(sequencer_rpc) = self.torii.torii_sequencer_rpc.as_ref().or(self.slot.slot_katana.as_ref()) {
             args.push("--rpc".to_string());
             args.push(sequencer_rpc.to_string());
         }
@@ -249,13 +402,12 @@ impl Config {
     }
 
     pub fn get_katana_args(&self) -> Vec<String> {
-        let mut args = vec![];
-
-        args.push("--allowed-origins".to_string());
-        args.push("*".to_string());
-
-        args.push("--db-dir".to_string());
-        args.push(format!("{}/katana-db", self.get_storage_base_dir()));
+        let mut args = vec![
+            "--allowed-origins".to_string(),
+            "*".to_string(),
+            "--db-dir".to_string(),
+            self.get_katana_db_dir().display().to_string(),
+        ];
 
         if self.katana.katana_dev {
             args.push("--dev".to_string())
@@ -281,6 +433,11 @@ impl Config {
 
         args.push("--json-log".to_string());
 
+        if let Some(metrics) = &self.katana.katana_metrics {
+            args.push("--metrics".to_string());
+            args.push(metrics.to_string());
+        }
+
         if let Some(fork_block_number) = &self.katana.katana_fork_block_number {
             args.push("--fork-block-number".to_string());
             args.push(fork_block_number.to_string())
@@ -313,12 +470,26 @@ impl Config {
         }
 
         args.push("--genesis".to_string());
-        args.push(format!("{}/config/genesis.json", self.get_storage_base_dir()));
+        args.push(self.get_genesis_path());
 
         args
     }
 
 
+    /**
+     * whether Katana runs locally, or Keiko fronts a deployed (slot) Katana
+     */
+    pub fn run_local_katana(&self) -> bool {
+        self.slot.slot_katana.is_none()
+    }
+
+    /**
+     * whether Torii runs locally, or Keiko fronts a deployed (slot) Torii
+     */
+    pub fn run_local_torii(&self) -> bool {
+        self.slot.slot_torii.is_none()
+    }
+
     /**
      * creates a json_rpc_client from katana
      */
@@ -332,14 +503,134 @@ impl Config {
      * creates the rpc_url
      */
     pub fn rpc_url(&self) -> Url {
-        Url::parse(LOCAL_KATANA).unwrap()
+        match &self.slot.slot_katana {
+            Some(slot_katana) => slot_katana.clone(),
+            None => Url::parse(LOCAL_KATANA).unwrap()
+        }
     }
 
     /*
     *    creates the torii_url
     */
     pub fn torii_url(&self) -> Url {
-        Url::parse(LOCAL_TORII).unwrap()
+        match &self.slot.slot_torii {
+            Some(slot_torii) => slot_torii.clone(),
+            None => Url::parse(LOCAL_TORII).unwrap()
+        }
+    }
+
+    /*
+    *    detects the versions of Keiko, Dojo and the local Katana and Torii
+    */
+    pub fn versions(&self) -> Versions {
+        Versions {
+            keiko: KEIKO_VERSION.trim().to_string(),
+            dojo: DOJO_VERSION.trim().to_string(),
+            katana: self.run_local_katana().then(|| utils::program_version("katana")).flatten(),
+            torii: self.run_local_torii().then(|| utils::program_version("torii")).flatten(),
+        }
+    }
+
+    /*
+    *    gets the effective node configuration, without secrets
+    */
+    pub fn node_config(&self, versions: Versions) -> NodeConfig {
+        let mining = if self.katana.katana_no_mining {
+            Mining::OnDemand
+        } else if let Some(block_time) = self.katana.katana_block_time {
+            Mining::Interval { block_time }
+        } else {
+            Mining::Instant
+        };
+
+        let fork = self.katana.katana_rpc_url.as_ref().map(|rpc_url| Fork {
+            rpc_url: redact_url(rpc_url),
+            block_number: self.katana.katana_fork_block_number,
+        });
+
+        NodeConfig {
+            world_address: self.katana.world_address.clone(),
+            chain_id: self.starknet.environment.chain_id.clone(),
+            local_katana: self.run_local_katana(),
+            local_torii: self.run_local_torii(),
+            disable_fee: self.starknet.disable_fee,
+            disable_validate: self.starknet.disable_validate,
+            mining,
+            fork,
+            gas_price: self.starknet.environment.gas_price,
+            validate_max_steps: self.starknet.environment.validate_max_steps,
+            invoke_max_steps: self.starknet.environment.invoke_max_steps,
+            tokens: self.starknet.tokens.clone(),
+            versions,
+        }
+    }
+
+    /*
+    *    whether the local Torii indexes the local Katana
+    */
+    pub fn torii_follows_katana(&self) -> bool {
+        self.run_local_katana() && self.run_local_torii() && self.torii.torii_sequencer_rpc.is_none()
+    }
+
+    /*
+    *    the config with a runtime update of the Katana options, all or nothing
+    */
+    pub fn with_katana_update(&self, update: &KatanaUpdate) -> Result<Config, Vec<String>> {
+        let mut config = self.clone();
+
+        if let Some(disable_fee) = update.disable_fee {
+            config.starknet.disable_fee = disable_fee;
+        }
+        if let Some(disable_validate) = update.disable_validate {
+            config.starknet.disable_validate = disable_validate;
+        }
+        if let Some(katana_no_mining) = update.katana_no_mining {
+            config.katana.katana_no_mining = katana_no_mining;
+        }
+        if let Some(katana_block_time) = update.katana_block_time {
+            config.katana.katana_block_time = katana_block_time;
+        }
+        if let Some(chain_id) = &update.chain_id {
+            config.starknet.environment.chain_id = chain_id.clone();
+        }
+        if let Some(gas_price) = update.gas_price {
+            config.starknet.environment.gas_price = gas_price;
+        }
+        if let Some(validate_max_steps) = update.validate_max_steps {
+            config.starknet.environment.validate_max_steps = validate_max_steps;
+        }
+        if let Some(invoke_max_steps) = update.invoke_max_steps {
+            config.starknet.environment.invoke_max_steps = invoke_max_steps;
+        }
+
+        if !config.run_local_katana() {
+            return Err(vec!["katana runs remotely (slot) and can't be reconfigured".to_string()]);
+        }
+
+        config.validate()?;
+
+        // like the genesis, Katana only takes the chain id when it creates its database
+        let katana_db_dir = self.get_katana_db_dir();
+        if config.starknet.environment.chain_id != self.starknet.environment.chain_id && genesis_builder::has_database(&katana_db_dir) {
+            return Err(vec![format!(
+                "chain_id can't change on the existing Katana database in {}, remove it first", katana_db_dir.display()
+            )]);
+        }
+
+        Ok(config)
+    }
+
+    /*
+    *    where Keiko scrapes the metrics of the local Katana
+    */
+    pub fn katana_metrics_url(&self) -> Option<Url> {
+        let mut metrics = self.katana.katana_metrics.filter(|_| self.run_local_katana())?;
+
+        if metrics.ip().is_unspecified() {
+            metrics.set_ip([127, 0, 0, 1].into());
+        }
+
+        Url::parse(&format!("http://{}", metrics)).ok()
     }
 
     /*
@@ -348,12 +639,72 @@ impl Config {
     pub fn server_state(&self) -> server_state::ServerState {
         let manifest_base_dir = format!("{}/manifests", self.get_storage_base_dir());
 
+        let metrics = Metrics::default();
+
         server_state::ServerState {
             json_rpc_client: self.json_rpc_client(),
             rpc_url: self.rpc_url(),
             manifest_base_dir,
+            storage_dir: self.get_storage_base_dir(),
             torii_url: self.torii_url(),
+            torii_db_path: self.run_local_torii().then(|| self.get_torii_db_path()),
+            katana_metrics_url: self.katana_metrics_url(),
             world_address: self.katana.world_address.clone(),
+            config: Arc::new(SharedConfig::new(self.clone())),
+            supervisor: Supervisor::new(metrics.clone()),
+            metrics,
+            accounts_file_lock: Arc::default(),
+            katana_update_lock: Arc::default(),
+            funding_lock: Arc::default(),
+        }
+    }
+}
+
+/// The config as the handlers see it, the Katana options can be updated at runtime.
+pub struct SharedConfig {
+    config: RwLock<Config>,
+    versions: Versions,
+}
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        let versions = config.versions();
+
+        Self {
+            config: RwLock::new(config),
+            versions,
         }
     }
 }
+
+impl ConfigSource for SharedConfig {
+    fn node_config(&self) -> NodeConfig {
+        self.config.read().unwrap().node_config(self.versions.clone())
+    }
+
+    fn katana_args(&self) -> Vec<String> {
+        self.config.read().unwrap().get_katana_args()
+    }
+
+    fn torii_args(&self) -> Vec<String> {
+        self.config.read().unwrap().get_torii_args()
+    }
+
+    fn torii_follows_katana(&self) -> bool {
+        self.config.read().unwrap().torii_follows_katana()
+    }
+
+    fn updated_katana_args(&self, update: &KatanaUpdate) -> Result<Vec<String>, Vec<String>> {
+        Ok(self.config.read().unwrap().with_katana_update(update)?.get_katana_args())
+    }
+
+    fn update_katana(&self, update: &KatanaUpdate) -> Result<(), Vec<String>> {
+        let mut config = self.config.write().unwrap();
+        *config = config.with_katana_update(update)?;
+        Ok(())
+    }
+
+    fn auth(&self) -> AuthConfig {
+        self.config.read().unwrap().auth.clone()
+    }
+}
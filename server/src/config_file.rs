@@ -0,0 +1,257 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use clap::{Arg, ArgAction, ArgMatches, Command, CommandFactory};
+use clap::parser::ValueSource;
+use serde::de::DeserializeOwned;
+use crate::args::KeikoArgs;
+
+/// The sections of keiko.toml, with the help heading of the options that belong in them.
+const SECTIONS: [(&str, &str); 6] = [
+    ("server", "Server options"),
+    ("starknet", "Starknet options"),
+    ("starknet.environment", "Environment options"),
+    ("katana", "Katana options"),
+    ("torii", "Torii options"),
+    ("slot", "Slot options"),
+];
+
+/// Options that only make sense on the command line.
+const CLI_ONLY: [&str; 2] = ["config", "print_config"];
+
+/// Sections that hold data rather than options, they're read on their own.
+const DATA_SECTIONS: [&str; 2] = ["auth", "genesis"];
+
+/**
+ * Layers the config file under env vars and flags: every option that is set in the config file,
+ * but not through an env var or a flag, is appended to the args as a flag.
+ *
+ * Returns all the problems found in the config file at once.
+ */
+pub fn layered_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Vec<OsString>, Vec<String>> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+
+    let command = KeikoArgs::command();
+    let matches = command.clone().ignore_errors(true).get_matches_from(args.clone());
+
+    let path = matches.get_one::<PathBuf>("config").cloned().unwrap_or_default();
+    let is_default_path = matches.value_source("config") == Some(ValueSource::DefaultValue);
+
+    if is_default_path && !path.exists() {
+        return Ok(args);
+    }
+
+    let file_name = path.display().to_string();
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|error| vec![format!("{}: {}", file_name, error)])?;
+
+    let table = contents.parse::<toml::Table>()
+        .map_err(|error| vec![format!("{}: {}", file_name, error)])?;
+
+    let mut layer = FileLayer {
+        file_name,
+        command: &command,
+        matches: &matches,
+        args: vec![],
+        errors: vec![],
+    };
+    layer.read_table("", table);
+
+    if !layer.errors.is_empty() {
+        return Err(layer.errors);
+    }
+
+    args.extend(layer.args);
+    Ok(args)
+}
+
+/**
+ * Reads one of the data sections of the config file, its default when there is no config file
+ * or the section isn't in it.
+ */
+pub fn data_section<T: DeserializeOwned + Default>(path: &Path, section: &str) -> Result<T, Vec<String>> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let file_name = path.display().to_string();
+
+    let contents = fs::read_to_string(path)
+        .map_err(|error| vec![format!("{}: {}", file_name, error)])?;
+
+    let mut table = contents.parse::<toml::Table>()
+        .map_err(|error| vec![format!("{}: {}", file_name, error)])?;
+
+    match table.remove(section) {
+        Some(value) => value.try_into().map_err(|error| vec![format!("{}: invalid `{}` section: {}", file_name, section, error)]),
+        None => Ok(T::default())
+    }
+}
+
+struct FileLayer<'a> {
+    file_name: String,
+    command: &'a Command,
+    matches: &'a ArgMatches,
+    args: Vec<OsString>,
+    errors: Vec<String>,
+}
+
+impl FileLayer<'_> {
+    fn read_table(&mut self, section: &str, table: toml::Table) {
+        for (key, value) in table {
+            let path = if section.is_empty() { key.clone() } else { format!("{}.{}", section, key) };
+
+            match value {
+                toml::Value::Table(table) if SECTIONS.iter().any(|(name, _)| *name == path) => {
+                    self.read_table(&path, table)
+                }
+                toml::Value::Table(_) if DATA_SECTIONS.contains(&path.as_str()) => {}
+                _ if section.is_empty() => self.errors.push(
+                    format!("{}: unknown section `{}`", self.file_name, path)
+                ),
+                value => self.read_value(section, &key, &path, value)
+            }
+        }
+    }
+
+    fn read_value(&mut self, section: &str, key: &str, path: &str, value: toml::Value) {
+        let heading = SECTIONS.iter()
+            .find(|(name, _)| *name == section)
+            .map(|(_, heading)| *heading);
+
+        let Some(arg) = self.command.get_arguments()
+            .filter(|arg| !CLI_ONLY.contains(&arg.get_id().as_str()))
+            .find(|arg| arg.get_id() == key && arg.get_help_heading() == heading) else {
+            self.errors.push(format!("{}: unknown key `{}`", self.file_name, path));
+            return;
+        };
+
+        let raw = match value {
+            // nothing to pass, `--tokens ""` would be a list with one empty value
+            toml::Value::Array(values) if values.is_empty() && arg.get_value_delimiter() == Some(',') => return,
+            toml::Value::String(value) => value,
+            toml::Value::Integer(value) => value.to_string(),
+            toml::Value::Float(value) => value.to_string(),
+            toml::Value::Boolean(value) => value.to_string(),
+            // lists of values are comma separated on the command line
+            toml::Value::Array(values) if arg.get_value_delimiter() == Some(',') => values.iter()
+                .map(|value| value.as_str().map_or_else(|| value.to_string(), str::to_string))
+                .collect::<Vec<String>>()
+                .join(","),
+            _ => {
+                self.errors.push(format!("{}: `{}` must be a string, number or boolean", self.file_name, path));
+                return;
+            }
+        };
+
+        let long = arg.get_long().unwrap_or(key);
+        let flag = format!("--{}", long);
+        let is_flag = matches!(arg.get_action(), ArgAction::SetTrue);
+
+        if let Err(reason) = validate(arg, &raw, is_flag) {
+            self.errors.push(format!("{}: invalid value `{}` for `{}`: {}", self.file_name, raw, path, reason));
+            return;
+        }
+
+        // env vars and flags take precedence over the config file
+        if matches!(self.matches.value_source(key), Some(ValueSource::EnvVariable | ValueSource::CommandLine)) {
+            return;
+        }
+
+        if is_flag {
+            if raw == "true" {
+                self.args.push(flag.into());
+            }
+        } else {
+            self.args.push(flag.into());
+            self.args.push(raw.into());
+        }
+    }
+}
+
+/**
+ * Runs a value through the value parser of its option, without the option's env var,
+ * requirements and conflicts getting in the way.
+ */
+fn validate(arg: &Arg, raw: &str, is_flag: bool) -> Result<(), String> {
+    if is_flag {
+        return match raw {
+            "true" | "false" => Ok(()),
+            _ => Err("expected a boolean".to_string())
+        };
+    }
+
+    let probe = Arg::new(arg.get_id().clone())
+        .long("value")
+        .action(arg.get_action().clone())
+        .value_parser(arg.get_value_parser().clone());
+
+    Command::new("keiko")
+        .no_binary_name(true)
+        .arg(probe)
+        .try_get_matches_from(["--value", raw])
+        .map(|_| ())
+        .map_err(|error| error.source().map(|source| source.to_string()).unwrap_or(error.kind().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use clap::Parser;
+    use crate::args::{Config, KeikoArgs};
+    use super::*;
+
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("keiko-{}-{}.toml", name, process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(path: &Path, flags: &[&str]) -> Result<Config, Vec<String>> {
+        let mut args = vec![OsString::from("keiko"), OsString::from("--config"), path.as_os_str().to_owned()];
+        args.extend(flags.iter().map(OsString::from));
+
+        let args = layered_args(args)?;
+        let keiko_args = KeikoArgs::try_parse_from(args).map_err(|error| vec![error.to_string()])?;
+        let config = Config::from(keiko_args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn printed_config_loads_back() {
+        let config = Config::from(KeikoArgs::parse_from(["keiko", "--world-address", "0x1", "--accounts", "1"]));
+        let printed = toml::to_string_pretty(&config).unwrap();
+        let path = config_file("printed", &printed);
+
+        let loaded = load(&path, &[]).expect("the printed config loads back");
+        assert_eq!(toml::to_string_pretty(&loaded).unwrap(), printed);
+    }
+
+    #[test]
+    fn empty_arrays_are_skipped() {
+        let path = config_file("empty-array", "[katana]\nworld_address = \"0x1\"\n\n[starknet]\ntokens = []\n");
+        let args = layered_args(["keiko", "--config", path.to_str().unwrap()].map(OsString::from)).unwrap();
+        assert!(!args.iter().any(|arg| arg == "--tokens"));
+        assert!(load(&path, &["--accounts", "1"]).unwrap().starknet.tokens.is_empty());
+    }
+
+    #[test]
+    fn flags_take_precedence_over_the_file() {
+        let path = config_file("precedence", "[server]\nport = 4000\n");
+
+        let args = layered_args(["keiko", "--config", path.to_str().unwrap(), "--port", "5000"].map(OsString::from)).unwrap();
+        assert_eq!(args.iter().filter(|arg| *arg == "--port").count(), 1);
+        assert!(args.contains(&OsString::from("5000")));
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_together() {
+        let path = config_file("unknown", "[server]\nnope = 1\n\n[nope]\nport = 1\n");
+
+        let errors = layered_args(["keiko", "--config", path.to_str().unwrap()].map(OsString::from)).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+    }
+}
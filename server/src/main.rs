@@ -1,24 +1,42 @@
+use std::future::Future;
+use std::io;
 use std::net::SocketAddr;
 use crate::args::{Config};
+use std::time::Duration;
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::oneshot;
 use tokio::task;
 use axum::http::Method;
-use axum::Router;
+use axum::{middleware, Router};
+use axum::extract::DefaultBodyLimit;
 use axum::routing::{get, get_service, MethodFilter, on};
 use tower_http::add_extension::AddExtensionLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::cors::{Any, CorsLayer};
-use keiko_api::handlers::{katana, keiko};
-use std::process::{Command, Stdio};
-use std::fs::File;
+use keiko_api::handlers::{auth, contracts, katana, keiko, scenarios, storage, torii, world};
+use keiko_api::metrics;
+use std::process::Command;
+use keiko_api::server_state::ServerState;
+use keiko_api::supervisor::{ChildSpec, Service};
 use axum::body::Body;
 use args::{KATANA_LOG, KEIKO_ASSETS, KEIKO_INDEX, TORII_LOG};
 use std::fs;
 use std::path::Path;
 
 
+/// How long in-flight requests get to finish on shutdown.
+const REQUEST_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// World exports are uploaded whole, far over axum's default of 2 MB.
+const WORLD_EXPORT_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Sierra classes of Dojo worlds are over axum's default of 2 MB too.
+const CONTRACT_CLASS_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
 mod args;
+mod config_file;
 mod utils;
+mod validation;
 
 #[tokio::main]
 async fn main() {
@@ -37,36 +55,99 @@ async fn main() {
             .unwrap();
     }
 
-    let katana = start_katana(&config).await;
+    if let Some(genesis) = config.genesis_config().filter(|_| config.run_local_katana()) {
+        match genesis.write(&config.get_genesis_path(), &config.get_katana_db_dir()) {
+            Ok(genesis) => println!("Generated the genesis with {} dev accounts", genesis.dev_accounts().len()),
+            Err(error) => {
+                eprintln!("error: failed to generate the genesis: {:#}", error);
+                std::process::exit(2);
+            }
+        }
+    }
 
-    let torii = start_torii(&config).await;
+    let server_state = config.server_state();
+
+    // listening before the children are started, so a signal during startup stops them too
+    let mut shutdown = Box::pin(shutdown_signal());
+
+    let started = tokio::select! {
+        started = start_children(&config, &server_state) => started.map_err(|error| error.to_string()),
+        signal = &mut shutdown => Err(format!("{} received", signal)),
+    };
+
+    if let Err(error) = started {
+        eprintln!("error: startup aborted: {}", error);
+        stop(&server_state, Service::Torii).await;
+        stop(&server_state, Service::Katana).await;
+        std::process::exit(1);
+    }
 
     // TODO Modify the Scarb.toml if needed with world address
 
     // TODO Deploy Dojo/contracts if needed
 
+    if config.server.auto_auth {
+        tokio::spawn(keiko_api::auth::watch_migrations(server_state.clone()));
+    }
+
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port.clone()));
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
 
-    let router = create_router(&config);
+    let router = create_router(&config, server_state.clone());
+
+    let (stop_server, server_stopped) = oneshot::channel::<()>();
 
     let server = axum::Server::bind(&addr)
-        .serve(router.into_make_service_with_connect_info::<SocketAddr>());
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async {
+            let _ = server_stopped.await;
+        });
 
-    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+    let mut server = task::spawn(server);
 
     tokio::select! {
-        _ = server => println!("Stopping server..."),
-        _ = sigterm.recv() => println!("sigterm received, stopping server...")
+        _ = &mut server => println!("Stopping server..."),
+        signal = &mut shutdown => {
+            println!("{} received, stopping server...", signal);
+            let _ = stop_server.send(());
+
+            // lets in-flight requests finish, but doesn't wait for long lived streams
+            if tokio::time::timeout(REQUEST_DRAIN_TIMEOUT, server).await.is_err() {
+                println!("Requests still in flight after {:?}, stopping anyway", REQUEST_DRAIN_TIMEOUT);
+            }
+        }
+    }
+
+    // Torii first, it indexes Katana
+    stop(&server_state, Service::Torii).await;
+    stop(&server_state, Service::Katana).await;
+}
+
+/**
+ * resolves on SIGTERM or SIGINT, the handlers are installed when it is called rather than when it is awaited
+ */
+fn shutdown_signal() -> impl Future<Output = &'static str> {
+    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+    let mut sigint = signal(SignalKind::interrupt()).unwrap();
+
+    async move {
+        tokio::select! {
+            _ = sigterm.recv() => "sigterm",
+            _ = sigint.recv() => "sigint"
+        }
     }
+}
 
-    // Close Katana and Torii
-    katana.abort();
-    torii.abort();
+async fn stop(server_state: &ServerState, service: Service) {
+    match server_state.supervisor.stop(service).await {
+        Ok(Some(_)) => println!("Stopped {}", service.name()),
+        Ok(None) => {}
+        Err(error) => eprintln!("Failed to stop {}: {}", service.name(), error)
+    }
 }
 
 
-fn create_router(config: &Config) -> Router<(), Body> {
+fn create_router(config: &Config, server_state: ServerState) -> Router<(), Body> {
     let cors = CorsLayer::new()
         // allow `GET` and `POST` when accessing the resource
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
@@ -76,49 +157,83 @@ fn create_router(config: &Config) -> Router<(), Body> {
 
 
     Router::new()
-        .route("/api/fund", get(katana::funds::handler))
+        .route("/api/fund", get(katana::funds::handler).post(katana::funds::handler))
         .route("/api/block", on(MethodFilter::POST, katana::block::handler))
-        // .route("/api/accounts", get(katana::account::handler))
+        .route("/api/accounts", get(katana::account::handler))
+        .route("/api/accounts/overview", get(katana::overview::handler))
+        .route("/api/accounts/burners", get(katana::burner::list_handler).post(katana::burner::handler))
+        .route("/api/katana/config", on(MethodFilter::POST, katana::config::handler))
+        .route("/api/torii/reindex", on(MethodFilter::POST, torii::reindex::handler))
+        .route("/api/torii/entities", on(MethodFilter::POST, torii::entities::handler))
+        .route("/api/torii/consistency", on(MethodFilter::POST, torii::consistency::handler))
+        .route("/api/storage/read", on(MethodFilter::POST, storage::slot::read_handler))
+        .route("/api/storage/write", on(MethodFilter::POST, storage::slot::write_handler))
+        .route("/api/storage/model/read", on(MethodFilter::POST, storage::model::read_handler))
+        .route("/api/storage/model/write", on(MethodFilter::POST, storage::model::write_handler))
+        .route("/api/contracts", get(contracts::list::handler))
+        .route("/api/contracts/call", on(MethodFilter::POST, contracts::call::handler))
+        .route("/api/contracts/invoke", on(MethodFilter::POST, contracts::invoke::handler))
+        .route("/api/contracts/impersonate", on(MethodFilter::POST, contracts::impersonate::handler))
+        .route("/api/contracts/declare", on(MethodFilter::POST, contracts::declare::handler).layer(DefaultBodyLimit::max(CONTRACT_CLASS_BODY_LIMIT)))
+        .route("/api/contracts/hash", on(MethodFilter::POST, contracts::hash::handler).layer(DefaultBodyLimit::max(CONTRACT_CLASS_BODY_LIMIT)))
+        .route("/api/scenarios/run", on(MethodFilter::POST, scenarios::run::handler))
+        .route("/api/auth", get(auth::list::handler))
+        .route("/api/auth/apply", on(MethodFilter::POST, auth::apply::handler))
+        .route("/api/world", get(world::inspect::handler))
+        .route("/api/world/export", get(world::export::handler))
+        .route("/api/world/import", on(MethodFilter::POST, world::import::handler).layer(DefaultBodyLimit::max(WORLD_EXPORT_BODY_LIMIT)))
+        .route("/api/world/diff", on(MethodFilter::POST, world::diff::handler).layer(DefaultBodyLimit::max(WORLD_EXPORT_BODY_LIMIT)))
         .route("/config", get(keiko::config::handler))
+        .route("/metrics", get(keiko::metrics::handler))
+        .route("/health", get(keiko::health::handler))
+        .route("/health/live", get(keiko::health::live_handler))
+        .route("/health/ready", get(keiko::health::ready_handler))
+        .route_layer(middleware::from_fn(metrics::track_requests))
         .nest_service("/keiko/assets", get_service(ServeDir::new(KEIKO_ASSETS)))
         .nest_service("/keiko", get_service(ServeFile::new(KEIKO_INDEX)))
         .nest_service("/assets", get_service(ServeDir::new(config.server.static_path.join("assets"))))
         .fallback_service(get_service(ServeFile::new(config.server.static_path.join("index.html"))))
         .layer(cors)
-        .layer(AddExtensionLayer::new(config.server_state()))
+        .layer(AddExtensionLayer::new(server_state))
 }
 
-async fn start_katana(config: &Config) -> task::JoinHandle<()> {
-    let katana_args = config.get_katana_args();
-
-    let result = task::spawn(async move {
-        let output = File::create(KATANA_LOG).expect("Failed to create file");
-
-        Command::new("katana")
-            .args(katana_args)
-            .stdout(Stdio::from(output))
-            .spawn()
-            .expect("Failed to start process");
-    });
-    // TODO get the server/port from args
-    utils::wait_for_port("127.0.0.1:5050".parse().unwrap()).await;
-    result
-}
+async fn start_children(config: &Config, server_state: &ServerState) -> io::Result<()> {
+    if config.run_local_katana() {
+        start_katana(server_state).await?;
+    } else {
+        println!("Using slot katana at {}", config.rpc_url());
+    }
 
-async fn start_torii(config: &Config) -> task::JoinHandle<()> {
-    let args = config.get_torii_args();
+    if config.run_local_torii() {
+        start_torii(server_state).await?;
+    } else {
+        println!("Using slot torii at {}", config.torii_url());
+    }
 
-    let result = task::spawn(async move {
-        let output = File::create(TORII_LOG).expect("Failed to create file");
+    Ok(())
+}
 
-        Command::new("torii")
-            .stdout(Stdio::from(output))
-            .args(args)
-            .spawn()
-            .expect("Failed to start torii");
-    });
+async fn start_katana(server_state: &ServerState) -> io::Result<()> {
+    let spec = ChildSpec {
+        program: "katana".to_string(),
+        args: server_state.config.katana_args(),
+        log_path: KATANA_LOG.to_string(),
+        // TODO get the server/port from args
+        addr: "127.0.0.1:5050".parse().unwrap(),
+    };
+
+    server_state.supervisor.start(Service::Katana, spec).await
+        .map_err(|error| io::Error::new(error.kind(), format!("failed to start katana: {}", error)))
+}
 
-    utils::wait_for_port("127.0.0.1:8080".parse().unwrap()).await;
+async fn start_torii(server_state: &ServerState) -> io::Result<()> {
+    let spec = ChildSpec {
+        program: "torii".to_string(),
+        args: server_state.config.torii_args(),
+        log_path: TORII_LOG.to_string(),
+        addr: "127.0.0.1:8080".parse().unwrap(),
+    };
 
-    result
-}
\ No newline at end of file
+    server_state.supervisor.start(Service::Torii, spec).await
+        .map_err(|error| io::Error::new(error.kind(), format!("failed to start torii: {}", error)))
+}
@@ -1,12 +1,14 @@
-use std::net::{SocketAddr};
-use tokio::net::TcpStream;
+use std::process::Command;
 
-pub async fn wait_for_port(addr: SocketAddr) {
-    loop {
-        if TcpStream::connect(addr).await.is_ok() {
-            break;
-        }
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await; // Sleep for a second
+/**
+ * runs `<program> --version`, None when the program isn't available
+ */
+pub fn program_version(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+
+    if !output.status.success() {
+        return None;
     }
-}
 
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+use keiko_api::genesis_builder;
+use crate::args::Config;
+
+impl Config {
+    /**
+     * checks the combination of options before anything is started, returns every problem at once
+     */
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+
+        self.validate_world(&mut errors);
+        self.validate_server(&mut errors);
+
+        if self.run_local_katana() {
+            self.validate_local_katana(&mut errors);
+        } else {
+            self.validate_slot_katana(&mut errors);
+        }
+
+        if !self.run_local_torii() {
+            self.validate_slot_torii(&mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_world(&self, errors: &mut Vec<String>) {
+        let world_address = &self.katana.world_address;
+
+        if world_address.is_empty() {
+            errors.push("world_address can't be empty".to_string());
+        } else if !is_felt(world_address) {
+            errors.push(format!("world_address `{}` is not a 0x prefixed hex address", world_address));
+        }
+
+        let chain_id = &self.starknet.environment.chain_id;
+
+        if chain_id.starts_with("0x") {
+            if !is_felt(chain_id) {
+                errors.push(format!("chain_id `{}` is not a valid hex chain id", chain_id));
+            }
+        } else if chain_id.is_empty() || chain_id.len() > 31 || !chain_id.is_ascii() {
+            errors.push(format!("chain_id `{}` must be 1 to 31 ascii characters", chain_id));
+        }
+
+        for token in &self.starknet.tokens {
+            if !is_felt(token) {
+                errors.push(format!("token `{}` is not a 0x prefixed hex address", token));
+            }
+        }
+    }
+
+    fn validate_server(&self, errors: &mut Vec<String>) {
+        if !self.server.static_path.is_dir() {
+            errors.push(format!("static_path `{}` is not a directory", self.server.static_path.display()));
+        }
+    }
+
+    fn validate_local_katana(&self, errors: &mut Vec<String>) {
+        let genesis_path = self.get_genesis_path();
+        let genesis_init_path = format!("storage_init/{}/config/genesis.json", self.katana.world_address);
+
+        // an empty storage dir is initialized from storage_init before Katana starts, or the genesis is generated
+        if self.genesis_config().is_none() && !Path::new(&genesis_path).is_file() && !Path::new(&genesis_init_path).is_file() {
+            errors.push(format!("genesis file not found at `{}` or `{}`", genesis_path, genesis_init_path));
+        }
+
+        if let Some(genesis) = self.genesis_config() {
+            if let Err(error) = genesis_builder::parse_amount(&genesis.eth_balance) {
+                errors.push(format!("account_balance: {}", error));
+            }
+
+            if let Some(Err(error)) = genesis.strk_balance.as_deref().map(genesis_builder::parse_amount) {
+                errors.push(format!("strk_balance: {}", error));
+            }
+        }
+
+        let katana_db_dir = self.get_katana_db_dir();
+
+        if katana_db_dir.exists() && !katana_db_dir.is_dir() {
+            errors.push(format!("katana_db_dir `{}` is not a directory", katana_db_dir.display()));
+        }
+
+        if self.katana.katana_no_mining && self.katana.katana_block_time.is_some() {
+            errors.push("katana_no_mining can't be combined with katana_block_time".to_string());
+        }
+
+        if self.katana.katana_block_time == Some(0) {
+            errors.push("katana_block_time must be at least 1 millisecond".to_string());
+        }
+
+        if self.katana.katana_fork_block_number.is_some() && self.katana.katana_rpc_url.is_none() {
+            errors.push("katana_fork_block_number requires katana_rpc_url".to_string());
+        }
+    }
+
+    fn validate_slot_katana(&self, errors: &mut Vec<String>) {
+        let local_only = [
+            ("katana_no_mining", self.katana.katana_no_mining),
+            ("katana_block_time", self.katana.katana_block_time.is_some()),
+            ("katana_db_dir", self.katana.katana_db_dir.is_some()),
+            ("katana_rpc_url", self.katana.katana_rpc_url.is_some()),
+            ("katana_fork_block_number", self.katana.katana_fork_block_number.is_some()),
+            ("katana_metrics", self.katana.katana_metrics.is_some()),
+            ("gas_price", self.starknet.environment.gas_price.is_some()),
+            ("validate_max_steps", self.starknet.environment.validate_max_steps.is_some()),
+            ("invoke_max_steps", self.starknet.environment.invoke_max_steps.is_some()),
+            ("seed", self.starknet.seed.is_some()),
+            ("accounts", self.starknet.accounts.is_some()),
+            ("account_balance", self.starknet.account_balance.is_some()),
+        ];
+
+        for (option, is_set) in local_only {
+            if is_set {
+                errors.push(format!("{} has no effect when slot_katana is set", option));
+            }
+        }
+    }
+
+    fn validate_slot_torii(&self, errors: &mut Vec<String>) {
+        if self.torii.torii_sequencer_rpc.is_some() {
+            errors.push("torii_sequencer_rpc has no effect when slot_torii is set".to_string());
+        }
+
+        if self.torii.torii_external_url.is_some() {
+            errors.push("torii_external_url has no effect when slot_torii is set".to_string());
+        }
+    }
+}
+
+fn is_felt(value: &str) -> bool {
+    match value.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.len() <= 64 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false
+    }
+}